@@ -0,0 +1,131 @@
+#![cfg_attr(test, deny(warnings))]
+#![deny(missing_docs)]
+
+//! # dynamic-derive
+//!
+//! `#[derive(Describe)]` for the `dynamic` crate, usually used through its
+//! `derive` feature.
+//!
+//! The derive implements `dynamic::Describe` with a descriptor recording the
+//! capabilities listed in the `describe` attribute:
+//!
+//! ```ignore
+//! #[derive(Clone, Debug, Describe)]
+//! #[describe(clone, debug)]
+//! struct Point { x: i32, y: i32 }
+//! ```
+//!
+//! Each capability requires the corresponding std trait: `clone` requires
+//! `Clone`, `debug` requires `Debug` and so on, as documented on
+//! `dynamic::DescriptorBuilder`. Without the attribute, the descriptor
+//! records no capabilities.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+// The capabilities accepted by the `describe` attribute, and the
+// DescriptorBuilder methods recording them.
+const CAPABILITIES: &[(&str, &str)] = &[
+    ("clone", "with_clone"),
+    ("debug", "with_debug"),
+    ("display", "with_display"),
+    ("error", "with_error"),
+    ("eq", "with_eq"),
+    ("hash", "with_hash"),
+    ("ord", "with_ord"),
+    ("default", "with_default")
+];
+
+/// Derive `dynamic::Describe`, with the capabilities selected by the
+/// `describe` attribute.
+#[proc_macro_derive(Describe, attributes(describe))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    let output = match expand(input) {
+        Ok(output) => output,
+        Err(message) => format!("compile_error!({:?});", message)
+    };
+
+    output.parse().unwrap()
+}
+
+fn expand(input: TokenStream) -> Result<String, String> {
+    let mut tokens = input.into_iter().peekable();
+    let mut methods = Vec::new();
+    let mut name = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Punct(ref punct) if punct.as_char() == '#' => {
+                if let Some(TokenTree::Group(attr)) = tokens.next() {
+                    methods.extend(parse_attribute(attr.stream())?);
+                }
+            },
+            TokenTree::Ident(ref ident) if is_item_keyword(&ident.to_string()) => {
+                name = match tokens.next() {
+                    Some(TokenTree::Ident(name)) => Some(name.to_string()),
+                    _ => return Err("expected the name of the type".to_owned())
+                };
+                break;
+            },
+            _ => ()
+        }
+    }
+
+    let name = name.ok_or_else(|| "Describe can only be derived for types".to_owned())?;
+
+    if let Some(TokenTree::Punct(punct)) = tokens.peek() {
+        if punct.as_char() == '<' {
+            return Err("Describe cannot be derived for generic types".to_owned());
+        }
+    }
+
+    Ok(format!(
+        "impl ::dynamic::Describe for {name} {{ \
+             fn descriptor() -> ::dynamic::Descriptor {{ \
+                 ::dynamic::Descriptor::builder::<{name}>(){methods}.build() \
+             }} \
+         }}",
+        name = name,
+        methods = methods.iter().map(|method| format!(".{}()", method)).collect::<String>()
+    ))
+}
+
+// Get the builder methods selected by an attribute, if it is a `describe`
+// attribute.
+fn parse_attribute(attr: TokenStream) -> Result<Vec<&'static str>, String> {
+    let mut tokens = attr.into_iter();
+
+    match tokens.next() {
+        Some(TokenTree::Ident(ref ident)) if ident.to_string() == "describe" => (),
+        _ => return Ok(Vec::new())
+    }
+
+    let list = match tokens.next() {
+        Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => group.stream(),
+        _ => return Err("expected a list of capabilities, as in `#[describe(clone, debug)]`".to_owned())
+    };
+
+    let mut methods = Vec::new();
+
+    for token in list {
+        match token {
+            TokenTree::Ident(ident) => {
+                let capability = ident.to_string();
+
+                match CAPABILITIES.iter().find(|&&(name, _)| name == capability) {
+                    Some(&(_, method)) => methods.push(method),
+                    None => return Err(format!("unknown capability `{}`", capability))
+                }
+            },
+            TokenTree::Punct(ref punct) if punct.as_char() == ',' => (),
+            token => return Err(format!("unexpected `{}` in the list of capabilities", token))
+        }
+    }
+
+    Ok(methods)
+}
+
+fn is_item_keyword(ident: &str) -> bool {
+    ident == "struct" || ident == "enum" || ident == "union"
+}
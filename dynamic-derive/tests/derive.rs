@@ -0,0 +1,49 @@
+extern crate dynamic;
+
+use dynamic::{Describe, Dynamic};
+
+#[derive(Clone, Debug, PartialEq, Describe)]
+#[describe(clone, debug)]
+struct Point {
+    x: i32,
+    y: i32
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default, Describe)]
+#[describe(clone, debug, hash, default)]
+enum Shape {
+    #[default]
+    Empty,
+    Square(u32)
+}
+
+#[derive(Describe)]
+struct Opaque;
+
+#[test]
+fn test_derive_clone_debug() {
+    let point = Dynamic::new_described(Point { x: 1, y: 2 });
+
+    let clone = point.clone();
+    assert_eq!(clone.downcast_ref::<Point>(), Some(&Point { x: 1, y: 2 }));
+    assert!(format!("{:?}", point).contains("Point { x: 1, y: 2 }"));
+
+    let desc = Point::descriptor();
+    assert!(desc.clone_fn().is_some() && desc.debug_fn().is_some());
+    assert!(desc.eq_fn().is_none());
+}
+
+#[test]
+fn test_derive_capabilities() {
+    let desc = Shape::descriptor();
+    assert!(desc.hash_fn().is_some() && desc.eq_fn().is_some());
+    assert!(desc.cmp_fn().is_none());
+    assert_eq!(desc.make_default().unwrap().downcast_ref::<Shape>(), Some(&Shape::Empty));
+
+    let square = Dynamic::new_described(Shape::Square(2));
+    assert!(*square == *Dynamic::new_described(Shape::Square(2)));
+
+    let opaque = Opaque::descriptor();
+    assert!(opaque.clone_fn().is_none() && opaque.debug_fn().is_none());
+    assert!(Dynamic::new_described(Opaque).is::<Opaque>());
+}
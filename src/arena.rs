@@ -0,0 +1,241 @@
+//! An arena for allocating many short-lived values of mixed types.
+
+use alloc::alloc::{alloc, dealloc, handle_alloc_error};
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::any::Any;
+use core::cell::{Cell, RefCell};
+use core::ptr::NonNull;
+use core::{cmp, fmt, mem};
+
+use {Described, Dynamic};
+
+// The size of the first chunk, in bytes. Each new chunk doubles the size of
+// the one before it.
+const MIN_CHUNK_SIZE: usize = 1024;
+
+/// A bump allocator for Dynamic values.
+///
+/// Values are allocated into large chunks of memory owned by the arena,
+/// rather than individually on the heap, and live as long as the arena
+/// or until it is reset. The destructors of all values allocated in the
+/// arena are run when it is reset or dropped.
+///
+/// ```
+/// use dynamic::DynamicArena;
+///
+/// let arena = DynamicArena::new();
+/// let x = arena.alloc(1u8);
+/// let y = arena.alloc(String::from("y"));
+///
+/// *x.downcast_mut::<u8>().unwrap() += 1;
+/// assert_eq!(x.downcast_ref::<u8>(), Some(&2));
+/// assert_eq!(y.downcast_ref::<String>().unwrap(), "y");
+/// ```
+#[derive(Default)]
+pub struct DynamicArena {
+    // Chunks are never moved or resized once allocated, so values stay put
+    // as new chunks are added.
+    chunks: RefCell<Vec<Chunk>>,
+
+    // The number of bytes used in the last chunk.
+    used: Cell<usize>,
+
+    // The values which need to be dropped, in allocation order.
+    values: RefCell<Vec<*mut Dynamic>>
+}
+
+impl DynamicArena {
+    /// Create a new, empty arena.
+    ///
+    /// No memory is allocated until the first value is.
+    #[inline]
+    pub fn new() -> DynamicArena { DynamicArena::default() }
+
+    /// Allocate a value in the arena.
+    ///
+    /// The resulting `Dynamic` can be downcasted back to a `T`, and lives as
+    /// long as the arena.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: Any>(&self, val: T) -> &mut Dynamic {
+        let ptr = self.alloc_layout(Layout::new::<Described<T>>()) as *mut Described<T>;
+
+        unsafe {
+            // The memory is suitably sized and aligned for a Described<T>,
+            // and is not part of any other allocation.
+            ptr.write(Described::new(val));
+            let dynamic = Dynamic::from_mut(&mut *ptr);
+
+            if mem::needs_drop::<T>() {
+                self.values.borrow_mut().push(dynamic as *mut Dynamic);
+            }
+
+            dynamic
+        }
+    }
+
+    /// Drop all values allocated in the arena, keeping its memory for new
+    /// values.
+    ///
+    /// Only the most recent, largest chunk of memory is kept.
+    pub fn reset(&mut self) {
+        self.drop_values();
+
+        let chunks = self.chunks.get_mut();
+        let len = chunks.len();
+        if len > 1 { chunks.drain(..len - 1); }
+        self.used.set(0);
+    }
+
+    /// Get the total number of bytes of memory owned by the arena.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.chunks.borrow().iter().map(|chunk| chunk.layout.size()).sum()
+    }
+
+    fn alloc_layout(&self, layout: Layout) -> *mut u8 {
+        let mut chunks = self.chunks.borrow_mut();
+
+        if let Some(chunk) = chunks.last() {
+            let used = self.used.get();
+            let start = used + unsafe { chunk.ptr.as_ptr().add(used) }.align_offset(layout.align());
+
+            if start + layout.size() <= chunk.layout.size() {
+                self.used.set(start + layout.size());
+                return unsafe { chunk.ptr.as_ptr().add(start) };
+            }
+        }
+
+        // Chunks are aligned for the value, so it can go at the start.
+        let last = chunks.last().map_or(0, |chunk| chunk.layout.size());
+        let size = cmp::max(cmp::max(last * 2, MIN_CHUNK_SIZE), layout.size());
+        let chunk = Chunk::new(Layout::from_size_align(size, layout.align()).unwrap());
+
+        let ptr = chunk.ptr.as_ptr();
+        chunks.push(chunk);
+        self.used.set(layout.size());
+        ptr
+    }
+
+    fn drop_values(&mut self) {
+        for dynamic in self.values.get_mut().drain(..) {
+            unsafe {
+                // The value is live until now, and its descriptor records how
+                // to drop it.
+                let dynamic = &mut *dynamic;
+                (dynamic.desc.drop)(dynamic.as_mut_ptr());
+            }
+        }
+    }
+}
+
+impl Drop for DynamicArena {
+    fn drop(&mut self) { self.drop_values() }
+}
+
+impl fmt::Debug for DynamicArena {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicArena")
+            .field("chunks", &self.chunks.borrow().len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+// A block of memory owned by an arena.
+struct Chunk {
+    ptr: NonNull<u8>,
+    layout: Layout
+}
+
+impl Chunk {
+    fn new(layout: Layout) -> Chunk {
+        // The layout is never zero-sized, since every Described contains a
+        // descriptor.
+        match NonNull::new(unsafe { alloc(layout) }) {
+            Some(ptr) => Chunk { ptr, layout },
+            None => handle_alloc_error(layout)
+        }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use DynamicArena;
+
+    struct Dropper(Rc<Cell<usize>>);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    #[repr(align(64))]
+    struct Aligned(u8);
+
+    #[test]
+    fn test_dynamic_arena_alloc() {
+        let arena = DynamicArena::new();
+        let x = arena.alloc(1i32);
+        let s = arena.alloc(String::from("s"));
+        let aligned = arena.alloc(Aligned(2));
+        let v = arena.alloc((0..100).collect::<Vec<u64>>());
+
+        *x.downcast_mut::<i32>().unwrap() += 1;
+        s.downcast_mut::<String>().unwrap().push('t');
+
+        assert_eq!(x.downcast_ref::<i32>(), Some(&2));
+        assert_eq!(s.downcast_ref::<String>().unwrap(), "st");
+        assert_eq!(aligned.downcast_ref::<Aligned>().unwrap().0, 2);
+        assert_eq!(aligned.as_ptr() as usize % 64, 0);
+        assert_eq!(v.downcast_ref::<Vec<u64>>().unwrap()[99], 99);
+        assert!(x.downcast_ref::<String>().is_none());
+    }
+
+    #[test]
+    fn test_dynamic_arena_grow() {
+        let arena = DynamicArena::new();
+        let values = (0..1000usize).map(|i| arena.alloc(i)).collect::<Vec<_>>();
+        assert!(arena.capacity() > 1024);
+
+        for (i, val) in values.iter().enumerate() {
+            assert_eq!(val.downcast_ref::<usize>(), Some(&i));
+        }
+
+        // Values larger than a chunk get a chunk of their own.
+        let big = arena.alloc([7u8; 4096]);
+        assert_eq!(big.downcast_ref::<[u8; 4096]>().unwrap()[4095], 7);
+    }
+
+    #[test]
+    fn test_dynamic_arena_drop() {
+        let drops = Rc::new(Cell::new(0));
+        let mut arena = DynamicArena::new();
+
+        for _ in 0..100 {
+            arena.alloc(Dropper(drops.clone()));
+            arena.alloc(1u8);
+        }
+        assert_eq!(drops.get(), 0);
+
+        arena.reset();
+        assert_eq!(drops.get(), 100);
+
+        let capacity = arena.capacity();
+        assert!(arena.alloc(Dropper(drops.clone())).is::<Dropper>());
+        assert_eq!(arena.capacity(), capacity);
+
+        drop(arena);
+        assert_eq!(drops.get(), 101);
+    }
+}
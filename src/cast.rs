@@ -0,0 +1,149 @@
+//! Casting Dynamic values to trait objects through static tables.
+
+use core::any::{Any, TypeId};
+use core::fmt;
+
+use Dynamic;
+
+/// A conversion from a type to a trait object type it implements.
+///
+/// Types opt into being recovered as a `Tr` from a Dynamic value by
+/// implementing this trait and being listed in a `TraitTable<Tr>`. The
+/// implementation is almost always the unsizing coercion; see `TraitTable`
+/// for an example.
+pub trait AsTraitObject<Tr: ?Sized> {
+    /// Convert a reference to this value into a trait object.
+    fn as_trait_object(&self) -> &Tr;
+}
+
+/// A table of the types which can be cast to the trait object type `Tr`.
+///
+/// Tables are built explicitly from a fixed list of types, usually in a
+/// `static`, so no global registration is involved:
+///
+/// ```
+/// use dynamic::{AsTraitObject, Dynamic, TraitTable};
+/// use std::fmt::Display;
+///
+/// struct Meters(f64);
+///
+/// impl Display for Meters {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "{}m", self.0)
+///     }
+/// }
+///
+/// impl AsTraitObject<dyn Display> for Meters {
+///     fn as_trait_object(&self) -> &(dyn Display + 'static) { self }
+/// }
+///
+/// static DISPLAY: TraitTable<dyn Display> = TraitTable::new(&[
+///     TraitTable::entry::<Meters>()
+/// ]);
+///
+/// let x = Dynamic::new(Meters(1.5));
+/// assert_eq!(x.downcast_trait_ref(&DISPLAY).unwrap().to_string(), "1.5m");
+/// ```
+///
+/// Lookups scan the table linearly, so tables are best kept small.
+pub struct TraitTable<Tr: ?Sized + 'static> {
+    entries: &'static [TraitEntry<Tr>]
+}
+
+/// An entry in a `TraitTable`, created by `TraitTable::entry`.
+pub struct TraitEntry<Tr: ?Sized + 'static> {
+    id: TypeId,
+    cast: fn(&Dynamic) -> &Tr
+}
+
+impl<Tr: ?Sized + 'static> TraitTable<Tr> {
+    /// Create a table from the given entries.
+    #[inline]
+    pub const fn new(entries: &'static [TraitEntry<Tr>]) -> TraitTable<Tr> {
+        TraitTable { entries }
+    }
+
+    /// Create the entry for casting `T` to `Tr`.
+    #[inline]
+    pub const fn entry<T: Any + AsTraitObject<Tr>>() -> TraitEntry<Tr> {
+        TraitEntry { id: TypeId::of::<T>(), cast: cast_value::<T, Tr> }
+    }
+
+    /// Check if the table contains the type with the given id.
+    #[inline]
+    pub fn contains_id(&self, id: TypeId) -> bool {
+        self.entries.iter().any(|entry| entry.id == id)
+    }
+
+    /// Cast the Dynamic value to `Tr`, if its contained type is in the table.
+    #[inline]
+    pub fn cast<'a>(&self, dynamic: &'a Dynamic) -> Option<&'a Tr> {
+        self.entries.iter()
+            .find(|entry| entry.id == dynamic.id())
+            .map(|entry| (entry.cast)(dynamic))
+    }
+}
+
+impl<Tr: ?Sized + 'static> fmt::Debug for TraitTable<Tr> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TraitTable")
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl Dynamic {
+    /// Cast the contained value to the trait object type `Tr`, using the
+    /// given table.
+    ///
+    /// Returns `None` if the contained type is not in the table.
+    #[inline]
+    pub fn downcast_trait_ref<'a, Tr: ?Sized + 'static>(&'a self, table: &TraitTable<Tr>) -> Option<&'a Tr> {
+        table.cast(self)
+    }
+}
+
+fn cast_value<T: Any + AsTraitObject<Tr>, Tr: ?Sized>(dynamic: &Dynamic) -> &Tr {
+    // Entries are only used for values with the id of T.
+    unsafe { dynamic.downcast_ref_unchecked::<T>() }.as_trait_object()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt::{self, Display};
+    use std::string::{String, ToString};
+
+    use {AsTraitObject, Dynamic, TraitTable};
+
+    struct X(usize);
+
+    impl Display for X {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "X({})", self.0)
+        }
+    }
+
+    impl AsTraitObject<dyn Display> for X {
+        fn as_trait_object(&self) -> &(dyn Display + 'static) { self }
+    }
+
+    impl AsTraitObject<dyn Display> for String {
+        fn as_trait_object(&self) -> &(dyn Display + 'static) { self }
+    }
+
+    static DISPLAY: TraitTable<dyn Display> = TraitTable::new(&[
+        TraitTable::entry::<X>(),
+        TraitTable::entry::<String>()
+    ]);
+
+    #[test]
+    fn test_downcast_trait_ref() {
+        let x = Dynamic::new(X(1));
+        let s = Dynamic::new(String::from("s"));
+
+        assert_eq!(x.downcast_trait_ref(&DISPLAY).unwrap().to_string(), "X(1)");
+        assert_eq!(s.downcast_trait_ref(&DISPLAY).unwrap().to_string(), "s");
+        assert!(Dynamic::new(1u8).downcast_trait_ref(&DISPLAY).is_none());
+        assert!(DISPLAY.contains_id(x.id()));
+    }
+}
@@ -0,0 +1,143 @@
+//! A clone-on-write Dynamic value.
+
+use alloc::boxed::Box;
+use core::fmt;
+use core::ops::Deref;
+
+use Dynamic;
+
+/// A Dynamic value which is either borrowed or owned, cloned lazily on
+/// the first mutation.
+///
+/// Reading goes through `Deref`, so `downcast_ref` and the other shared
+/// accessors of `Dynamic` are available directly. Mutable access is only
+/// available through `DynCow::to_mut`, which clones a borrowed value into an
+/// owned one first.
+pub enum DynCow<'a> {
+    /// A borrowed Dynamic value.
+    Borrowed(&'a Dynamic),
+
+    /// An owned Dynamic value.
+    Owned(Box<Dynamic>)
+}
+
+impl<'a> DynCow<'a> {
+    /// Check if the value is borrowed.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        match *self {
+            DynCow::Borrowed(_) => true,
+            DynCow::Owned(_) => false
+        }
+    }
+
+    /// Check if the value is owned.
+    #[inline]
+    pub fn is_owned(&self) -> bool { !self.is_borrowed() }
+
+    /// Get a mutable reference to the value, cloning it if it is borrowed.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the value is borrowed and was not created with cloning
+    /// support.
+    pub fn to_mut(&mut self) -> &mut Dynamic {
+        if let DynCow::Borrowed(borrowed) = *self {
+            *self = DynCow::Owned(borrowed.try_clone().unwrap_or_else(|| {
+                panic!("a borrowed Dynamic value of type `{}` was mutated, but was \
+                        not created with cloning support", borrowed.descriptor().name())
+            }));
+        }
+
+        match *self {
+            DynCow::Owned(ref mut owned) => owned,
+            DynCow::Borrowed(_) => unreachable!()
+        }
+    }
+
+    /// Get an owned value, cloning it if it is borrowed.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the value is borrowed and was not created with cloning
+    /// support.
+    pub fn into_owned(mut self) -> Box<Dynamic> {
+        self.to_mut();
+
+        match self {
+            DynCow::Owned(owned) => owned,
+            DynCow::Borrowed(_) => unreachable!()
+        }
+    }
+}
+
+impl<'a> Deref for DynCow<'a> {
+    type Target = Dynamic;
+
+    fn deref(&self) -> &Dynamic {
+        match *self {
+            DynCow::Borrowed(borrowed) => borrowed,
+            DynCow::Owned(ref owned) => owned
+        }
+    }
+}
+
+impl<'a> From<&'a Dynamic> for DynCow<'a> {
+    fn from(borrowed: &'a Dynamic) -> DynCow<'a> { DynCow::Borrowed(borrowed) }
+}
+
+impl<'a> From<Box<Dynamic>> for DynCow<'a> {
+    fn from(owned: Box<Dynamic>) -> DynCow<'a> { DynCow::Owned(owned) }
+}
+
+impl<'a> fmt::Debug for DynCow<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DynCow::Borrowed(borrowed) => f.debug_tuple("Borrowed").field(&borrowed).finish(),
+            DynCow::Owned(ref owned) => f.debug_tuple("Owned").field(owned).finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::string::String;
+
+    use {DynCow, Dynamic};
+
+    #[test]
+    fn test_dyn_cow_read() {
+        let original = Dynamic::new_cloneable(String::from("read"));
+        let cow = DynCow::from(&*original);
+
+        assert!(cow.is_borrowed());
+        assert_eq!(cow.downcast_ref::<String>().unwrap(), "read");
+        assert!(cow.downcast_ref::<u8>().is_none());
+        assert!(cow.is_borrowed());
+    }
+
+    #[test]
+    fn test_dyn_cow_write() {
+        let original = Dynamic::new_cloneable(String::from("write"));
+        let mut cow = DynCow::from(&*original);
+
+        cow.to_mut().downcast_mut::<String>().unwrap().push('!');
+        assert!(cow.is_owned());
+        assert_eq!(cow.downcast_ref::<String>().unwrap(), "write!");
+        assert_eq!(original.downcast_ref::<String>().unwrap(), "write");
+
+        let owned = cow.into_owned();
+        assert_eq!(owned.downcast_ref::<String>().unwrap(), "write!");
+
+        let mut cow = DynCow::from(Dynamic::new(1u8));
+        *cow.to_mut().downcast_mut::<u8>().unwrap() += 1;
+        assert_eq!(cow.downcast_ref::<u8>(), Some(&2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_dyn_cow_write_uncloneable() {
+        let original = Dynamic::new(1u8);
+        DynCow::from(&*original).to_mut();
+    }
+}
@@ -0,0 +1,216 @@
+//! Building descriptors with several capabilities, and types which describe
+//! themselves.
+
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use core::error::Error;
+use core::fmt;
+use core::hash::Hash;
+use core::marker::PhantomData;
+
+use {Described, Descriptor, Dynamic};
+use {clone_value, cmp_values, debug_value, default_value, display_value, eq_values, error_value,
+     hash_value};
+
+/// A builder for a Descriptor of the type `T` which records several
+/// capabilities, created by `Descriptor::builder`.
+///
+/// Each of the `Descriptor::new_*` constructors records a single
+/// capability. The builder records any combination of them:
+///
+/// ```
+/// use dynamic::Descriptor;
+///
+/// const STRING: Descriptor = Descriptor::builder::<String>()
+///     .with_clone()
+///     .with_debug()
+///     .build();
+///
+/// assert!(STRING.clone_fn().is_some());
+/// assert!(STRING.debug_fn().is_some());
+/// assert!(STRING.eq_fn().is_none());
+/// ```
+pub struct DescriptorBuilder<T> {
+    desc: Descriptor,
+    marker: PhantomData<fn() -> T>
+}
+
+impl Descriptor {
+    /// Start building a Descriptor describing the type `T`.
+    #[inline]
+    pub const fn builder<T: Any>() -> DescriptorBuilder<T> {
+        DescriptorBuilder { desc: Descriptor::new::<T>(), marker: PhantomData }
+    }
+}
+
+impl<T: Any> DescriptorBuilder<T> {
+    /// Record how to clone values of `T`, as in `Descriptor::new_cloneable`.
+    #[inline]
+    pub const fn with_clone(mut self) -> DescriptorBuilder<T> where T: Clone {
+        self.desc.clone = Some(clone_value::<T>);
+        self
+    }
+
+    /// Record how to format values of `T` using `Debug`, as in
+    /// `Descriptor::new_debug`.
+    #[inline]
+    pub const fn with_debug(mut self) -> DescriptorBuilder<T> where T: fmt::Debug {
+        self.desc.debug = Some(debug_value::<T>);
+        self
+    }
+
+    /// Record how to format values of `T` using `Display`, as in
+    /// `Descriptor::new_display`.
+    #[inline]
+    pub const fn with_display(mut self) -> DescriptorBuilder<T> where T: fmt::Display {
+        self.desc.display = Some(display_value::<T>);
+        self
+    }
+
+    /// Record how to view values of `T` as errors, and how to format them,
+    /// as in `Descriptor::new_error`.
+    #[inline]
+    pub const fn with_error(mut self) -> DescriptorBuilder<T> where T: Error {
+        self.desc.error = Some(error_value::<T>);
+        self.with_debug().with_display()
+    }
+
+    /// Record how to compare values of `T` for equality, as in
+    /// `Descriptor::new_eq`.
+    #[inline]
+    pub const fn with_eq(mut self) -> DescriptorBuilder<T> where T: Eq {
+        self.desc.eq = Some(eq_values::<T>);
+        self
+    }
+
+    /// Record how to hash and compare values of `T`, as in
+    /// `Descriptor::new_hash`.
+    #[inline]
+    pub const fn with_hash(mut self) -> DescriptorBuilder<T> where T: Hash + Eq {
+        self.desc.hash = Some(hash_value::<T>);
+        self.with_eq()
+    }
+
+    /// Record how to order and compare values of `T`, as in
+    /// `Descriptor::new_ord`.
+    #[inline]
+    pub const fn with_ord(mut self) -> DescriptorBuilder<T> where T: Ord {
+        self.desc.cmp = Some(cmp_values::<T>);
+        self.with_eq()
+    }
+
+    /// Record how to create default values of `T`, as in
+    /// `Descriptor::new_default`.
+    #[inline]
+    pub const fn with_default(mut self) -> DescriptorBuilder<T> where T: Default {
+        self.desc.default = Some(default_value::<T>);
+        self
+    }
+
+    /// Finish building the descriptor.
+    #[inline]
+    pub const fn build(self) -> Descriptor { self.desc }
+}
+
+impl<T> fmt::Debug for DescriptorBuilder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("DescriptorBuilder").field(&self.desc).finish()
+    }
+}
+
+/// A type which knows its own descriptor, including whichever capabilities
+/// it supports.
+///
+/// With the `derive` feature, this can be derived, selecting capabilities
+/// with the `describe` attribute:
+///
+/// ```ignore
+/// #[derive(Clone, Debug, PartialEq, Eq, Describe)]
+/// #[describe(clone, debug, eq)]
+/// struct Point { x: i32, y: i32 }
+/// ```
+///
+/// The capabilities are `clone`, `debug`, `display`, `error`, `eq`, `hash`,
+/// `ord` and `default`, each recorded as by the `DescriptorBuilder` method
+/// of the same name.
+pub trait Describe: Any {
+    /// Get the descriptor of this type.
+    ///
+    /// The descriptor must describe `Self`, as if created by
+    /// `Descriptor::builder::<Self>()`. Since descriptors can only be
+    /// created for the type they describe, a descriptor of any other type
+    /// is detected by `Dynamic::new_described`, so implementing this trait
+    /// is safe.
+    fn descriptor() -> Descriptor;
+}
+
+impl Dynamic {
+    /// Create a new, heap-allocated Dynamic value described by the
+    /// descriptor of its type, as given by `Describe`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `T::descriptor()` does not describe `T`.
+    #[inline]
+    pub fn new_described<T: Describe>(val: T) -> Box<Dynamic> {
+        let desc = T::descriptor();
+
+        // The layout and functions of a descriptor always agree with its id.
+        assert!(desc.id() == TypeId::of::<T>(),
+                "the descriptor of `{}` describes `{}`", Descriptor::new::<T>().name(), desc.name());
+
+        Dynamic::from_described(Described { desc, data: val })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fmt;
+    use std::format;
+    use std::string::String;
+
+    use {Describe, Descriptor, Dynamic};
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+    struct X(usize);
+
+    impl Describe for X {
+        fn descriptor() -> Descriptor {
+            Descriptor::builder::<X>().with_clone().with_debug().with_hash().build()
+        }
+    }
+
+    struct Wrong;
+
+    impl Describe for Wrong {
+        fn descriptor() -> Descriptor { Descriptor::new::<X>() }
+    }
+
+    #[test]
+    fn test_descriptor_builder() {
+        let desc = Descriptor::builder::<String>().with_display().with_default().build();
+        assert_eq!(desc, Descriptor::new::<String>());
+        assert!(desc.display_fn().is_some() && desc.default_fn().is_some());
+        assert!(desc.clone_fn().is_none() && desc.eq_fn().is_none());
+        assert_eq!(desc.make_default().unwrap().downcast_ref::<String>().unwrap(), "");
+
+        let error = Descriptor::builder::<fmt::Error>().with_error().build();
+        assert!(error.debug_fn().is_some() && error.display_fn().is_some());
+    }
+
+    #[test]
+    fn test_new_described() {
+        let x = Dynamic::new_described(X(1));
+        assert_eq!(x.clone().downcast_ref::<X>(), Some(&X(1)));
+        assert!(format!("{:?}", x).contains("X(1)"));
+        assert!(*x == *Dynamic::new_described(X(1)));
+        assert!(x.descriptor().hash_fn().is_some());
+        assert!(x.descriptor().display_fn().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "describes")]
+    fn test_new_described_wrong_descriptor() {
+        Dynamic::new_described(Wrong);
+    }
+}
@@ -0,0 +1,230 @@
+//! A C interface for inspecting and freeing Dynamic values.
+//!
+//! Dynamic values always originate in Rust, since creating one requires
+//! knowing the Rust type of the value. Rust code hands them to C as opaque
+//! `DynamicHandle`s, and C code can then inspect them with the `dynamic_*`
+//! functions and free them with `dynamic_free`.
+
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use core::ffi::c_void;
+use core::mem;
+
+use Dynamic;
+
+/// An opaque representation of a `TypeId`, which can be compared with
+/// `dynamic_is`.
+///
+/// The bytes are a copy of the whole `TypeId`, so two `FfiTypeId`s are equal
+/// exactly when they identify the same type. They have no meaning other
+/// than identifying the type within the process which created them, and
+/// must not be persisted or sent to another process.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct FfiTypeId {
+    /// The bytes of the `TypeId`.
+    pub bytes: [u8; mem::size_of::<TypeId>()]
+}
+
+impl FfiTypeId {
+    /// Get the opaque representation of the id of `T`.
+    #[inline]
+    pub fn of<T: Any>() -> FfiTypeId { FfiTypeId::from(TypeId::of::<T>()) }
+}
+
+impl From<TypeId> for FfiTypeId {
+    #[inline]
+    fn from(id: TypeId) -> FfiTypeId {
+        // Every byte of a TypeId is initialized, so it can be copied out as
+        // bytes, which are only ever compared.
+        FfiTypeId { bytes: unsafe { mem::transmute::<TypeId, [u8; mem::size_of::<TypeId>()]>(id) } }
+    }
+}
+
+/// The parts of a `Descriptor` which are meaningful to C code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct FfiDescriptor {
+    /// The size of the type, in bytes.
+    pub size: usize,
+
+    /// The alignment of the type, in bytes.
+    pub alignment: usize,
+
+    /// The opaque id of the type.
+    pub type_id: FfiTypeId
+}
+
+/// An opaque, single-pointer handle to a Dynamic value, for passing to C.
+///
+/// `Box<Dynamic>` is a fat pointer, made of a data pointer and a vtable
+/// pointer, which has no C equivalent. A handle instead points to a second
+/// allocation holding the `Box<Dynamic>`. It is `#[repr(transparent)]`, so
+/// it has the same representation as a single pointer, and C code can
+/// treat it as an opaque `void *`.
+///
+/// A handle owns its value, but does not free it when dropped: it must
+/// eventually be freed by `dynamic_free` or converted back with
+/// `DynamicHandle::into_box`.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct DynamicHandle(*mut Box<Dynamic>);
+
+impl DynamicHandle {
+    /// Convert a Dynamic value into a handle.
+    #[inline]
+    pub fn from_box(val: Box<Dynamic>) -> DynamicHandle {
+        DynamicHandle(Box::into_raw(Box::new(val)))
+    }
+
+    /// Convert the handle back into a Dynamic value.
+    ///
+    /// ## Safety
+    ///
+    /// The handle must have been created by `from_box`, and must not have
+    /// been freed by `dynamic_free`.
+    #[inline]
+    pub unsafe fn into_box(self) -> Box<Dynamic> {
+        *Box::from_raw(self.0)
+    }
+
+    /// Check if the handle is null.
+    #[inline]
+    pub fn is_null(&self) -> bool { self.0.is_null() }
+
+    /// Get the raw pointer behind the handle.
+    #[inline]
+    pub fn as_raw(&self) -> *mut c_void { self.0 as *mut c_void }
+
+    /// Create a handle from a raw pointer returned by `as_raw`.
+    ///
+    /// ## Safety
+    ///
+    /// `raw` must be null, or have been returned by `as_raw` for a handle
+    /// which is still live.
+    #[inline]
+    pub unsafe fn from_raw(raw: *mut c_void) -> DynamicHandle {
+        DynamicHandle(raw as *mut Box<Dynamic>)
+    }
+
+    // Borrow the Dynamic value behind the handle.
+    #[inline]
+    unsafe fn get<'a>(&self) -> &'a mut Dynamic {
+        &mut *self.0
+    }
+}
+
+/// Read the descriptor of the value behind a handle.
+///
+/// ## Safety
+///
+/// `handle` must be a live handle created by `DynamicHandle::from_box`.
+#[no_mangle]
+pub unsafe extern "C" fn dynamic_descriptor(handle: DynamicHandle) -> FfiDescriptor {
+    let desc = handle.get().descriptor();
+
+    FfiDescriptor {
+        size: desc.size(),
+        alignment: desc.alignment(),
+        type_id: FfiTypeId::from(desc.id())
+    }
+}
+
+/// Check if the value behind a handle has the given type id.
+///
+/// The ids are compared exactly, so if this returns `true`, the pointer
+/// returned by `dynamic_data_ptr` points to a value of the type identified
+/// by `type_id`.
+///
+/// ## Safety
+///
+/// `handle` must be a live handle created by `DynamicHandle::from_box`, and `type_id`
+/// must point to a valid `FfiTypeId`.
+#[no_mangle]
+pub unsafe extern "C" fn dynamic_is(handle: DynamicHandle, type_id: *const FfiTypeId) -> bool {
+    FfiTypeId::from(handle.get().id()) == *type_id
+}
+
+/// Get a pointer to the value behind a handle.
+///
+/// The pointer is valid until the handle is freed.
+///
+/// ## Safety
+///
+/// `handle` must be a live handle created by `DynamicHandle::from_box`.
+#[no_mangle]
+pub unsafe extern "C" fn dynamic_data_ptr(handle: DynamicHandle) -> *mut c_void {
+    handle.get().as_mut_ptr() as *mut c_void
+}
+
+/// Free a handle and the value behind it.
+///
+/// Null handles are ignored.
+///
+/// ## Safety
+///
+/// `handle` must be null or a live handle created by
+/// `DynamicHandle::from_box`, and must not be used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dynamic_free(handle: DynamicHandle) {
+    if !handle.is_null() {
+        drop(handle.into_box());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::ptr;
+    use std::rc::Rc;
+
+    use super::*;
+
+    struct Dropper(Rc<Cell<usize>>, u32);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    #[test]
+    fn test_ffi() {
+        let drops = Rc::new(Cell::new(0));
+        let handle = DynamicHandle::from_box(Dynamic::new(Dropper(drops.clone(), 7)));
+        let raw = handle.as_raw();
+
+        unsafe {
+            let desc = dynamic_descriptor(DynamicHandle::from_raw(raw));
+            assert_eq!(desc.size, mem::size_of::<Dropper>());
+            assert_eq!(desc.alignment, mem::align_of::<Dropper>());
+            assert_eq!(desc.type_id, FfiTypeId::of::<Dropper>());
+            assert_eq!(desc.type_id, FfiTypeId::from(TypeId::of::<Dropper>()));
+
+            assert!(dynamic_is(DynamicHandle::from_raw(raw), &FfiTypeId::of::<Dropper>()));
+            assert!(!dynamic_is(DynamicHandle::from_raw(raw), &FfiTypeId::of::<u32>()));
+
+            let data = dynamic_data_ptr(DynamicHandle::from_raw(raw)) as *mut Dropper;
+            (*data).1 += 1;
+
+            let val = handle.into_box();
+            assert_eq!(val.downcast_ref::<Dropper>().unwrap().1, 8);
+
+            let handle = DynamicHandle::from_box(val);
+            assert_eq!(drops.get(), 0);
+            dynamic_free(handle);
+            assert_eq!(drops.get(), 1);
+
+            dynamic_free(DynamicHandle::from_raw(ptr::null_mut()));
+        }
+    }
+
+    #[test]
+    fn test_handle_representation() {
+        assert_eq!(mem::size_of::<DynamicHandle>(), mem::size_of::<*mut c_void>());
+        assert_eq!(mem::size_of::<Box<Dynamic>>(), 2 * mem::size_of::<*mut c_void>());
+
+        let handle = DynamicHandle::from_box(Dynamic::new(1u8));
+        assert!(!handle.is_null());
+        let back = unsafe { handle.into_box() };
+        assert_eq!(back.downcast_ref::<u8>(), Some(&1));
+    }
+}
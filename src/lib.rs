@@ -1,6 +1,8 @@
 #![cfg_attr(test, deny(warnings))]
 #![cfg_attr(feature = "nightly", feature(test))]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 #![deny(missing_docs)]
+#![no_std]
 
 //! # dynamic
 //!
@@ -10,13 +12,74 @@
 //! `Dynamic` is similar to `Any` from `std::any::Any`, except that downcasting
 //! does not involve any virtual calls since the `TypeId` of the contained
 //! value is pre-computed.
+//!
+//! ## `no_std`
+//!
+//! The crate only requires `core` and `alloc`. Disabling the default `std`
+//! feature builds it without linking against `std`, leaving out the
+//! containers which depend on `std`, such as `DynamicMap`.
+
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+#[cfg(feature = "derive")]
+extern crate dynamic_derive;
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use core::alloc::Layout;
+#[cfg(feature = "allocator_api")]
+use core::alloc::Allocator;
+use core::any::{self, TypeId, Any};
+use core::borrow::{Borrow, BorrowMut};
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::error::Error;
+use core::hash::{Hash, Hasher};
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::{fmt, mem, ptr, slice};
 
-extern crate unsafe_any as uany;
+pub use arena::DynamicArena;
+pub use cast::{AsTraitObject, TraitEntry, TraitTable};
+pub use cow::DynCow;
+pub use describe::{Describe, DescriptorBuilder};
+#[cfg(feature = "derive")]
+pub use dynamic_derive::Describe;
+#[doc(hidden)]
+pub use macros::__dynamic_vec;
+#[cfg(feature = "std")]
+pub use map::{DynamicMap, Entry};
+pub use pool::DynamicPool;
+#[cfg(feature = "std")]
+pub use registry::{PluginArgs, PluginRegistry, Registry, TypeRegistry};
+pub use small::SmallDynamic;
+pub use stack::{DynamicStack, PopError};
+pub use sync::{DynamicSend, DynamicSync, DynamicSendSync};
+pub use vec::{DynamicVec, downcast_iter, downcast_iter_mut};
 
-use uany::UnsafeAnyExt;
+pub mod ffi;
 
-use std::any::{TypeId, Any};
-use std::{fmt, mem};
+#[macro_use]
+mod macros;
+
+mod arena;
+mod cast;
+mod cow;
+mod describe;
+#[cfg(feature = "std")]
+mod map;
+mod pool;
+#[cfg(feature = "std")]
+mod registry;
+mod small;
+mod stack;
+mod sync;
+mod vec;
 
 /// A dynamically typed value.
 ///
@@ -26,9 +89,21 @@ use std::{fmt, mem};
 ///
 /// Not Sized, since the size of the type is determined at runtime, so must be
 /// used behind a pointer (e.g. `&Dynamic`, `Box<Dynamic>`, etc.)
+#[repr(C)]
 pub struct Dynamic {
-    id: TypeId,
-    data: Dyn
+    // A Dynamic is always created by unsizing a `Described<T>` to a
+    // `Described<dyn Dyn>`, then casting the pointer to a `Dynamic`. Both
+    // are `#[repr(C)]` with the same fields, so the descriptor is at offset
+    // zero and the data follows at the same offset in both: the offset of
+    // the unsized data is computed from the alignment in the vtable, which
+    // is that of T. The vtable also gives the size and alignment of the
+    // whole, so a Dynamic deallocates with the layout of its Described<T>.
+    //
+    // Downcasting reverses this. Casting a `*mut Dynamic` to a
+    // `*mut Described<T>` discards the vtable and keeps the address, which
+    // is that of the original Described<T>.
+    desc: Descriptor,
+    data: dyn Dyn
 }
 
 impl Dynamic {
@@ -37,12 +112,173 @@ impl Dynamic {
     /// The resulting `Dynamic` can be downcasted back to a `T`.
     #[inline]
     pub fn new<T: Any>(val: T) -> Box<Dynamic> {
-        let un_sized = Box::new(Described {
-            id: TypeId::of::<T>(),
+        Dynamic::from_described(Described::new(val))
+    }
+
+    /// Create a new, heap-allocated Dynamic value which can be cloned
+    /// without knowing its type.
+    ///
+    /// See `Descriptor::new_cloneable`.
+    #[inline]
+    pub fn new_cloneable<T: Any + Clone>(val: T) -> Box<Dynamic> {
+        Dynamic::from_described(Described {
+            desc: Descriptor::new_cloneable::<T>(),
+            data: val
+        })
+    }
+
+    /// Create a new, heap-allocated Dynamic value whose `Debug` output
+    /// includes the contained value.
+    ///
+    /// See `Descriptor::new_debug`.
+    #[inline]
+    pub fn new_debug<T: Any + fmt::Debug>(val: T) -> Box<Dynamic> {
+        Dynamic::from_described(Described {
+            desc: Descriptor::new_debug::<T>(),
+            data: val
+        })
+    }
+
+    /// Create a new, heap-allocated Dynamic value which can be formatted
+    /// using `Display`.
+    ///
+    /// Values created by other constructors, such as `Dynamic::new`, cannot
+    /// be displayed. See `Descriptor::new_display`.
+    ///
+    /// ```
+    /// use dynamic::Dynamic;
+    /// use std::fmt::Write;
+    ///
+    /// let name = String::from("dynamic");
+    /// assert_eq!(format!("{}", Dynamic::new_display(name.clone())), name);
+    ///
+    /// // Displaying a value without display support is an error.
+    /// assert!(write!(String::new(), "{}", Dynamic::new(name)).is_err());
+    /// ```
+    #[inline]
+    pub fn new_display<T: Any + fmt::Display>(val: T) -> Box<Dynamic> {
+        Dynamic::from_described(Described {
+            desc: Descriptor::new_display::<T>(),
+            data: val
+        })
+    }
+
+    /// Create a new, heap-allocated Dynamic value which can be viewed as a
+    /// std `Error` trait object.
+    ///
+    /// See `Dynamic::as_error` and `Dynamic::into_error`. The value can also
+    /// be formatted with `Debug` and `Display`, as if created by
+    /// `Dynamic::new_debug` and `Dynamic::new_display`.
+    #[inline]
+    pub fn new_error<T: Any + Error>(val: T) -> Box<Dynamic> {
+        Dynamic::from_described(Described {
+            desc: Descriptor::new_error::<T>(),
+            data: val
+        })
+    }
+
+    /// Create a new, heap-allocated Dynamic value which can be compared
+    /// for equality with other Dynamic values.
+    ///
+    /// See `Descriptor::new_eq`.
+    #[inline]
+    pub fn new_eq<T: Any + Eq>(val: T) -> Box<Dynamic> {
+        Dynamic::from_described(Described {
+            desc: Descriptor::new_eq::<T>(),
+            data: val
+        })
+    }
+
+    /// Create a new, heap-allocated Dynamic value which can be hashed and
+    /// compared for equality with other Dynamic values.
+    ///
+    /// See `Descriptor::new_hash`.
+    #[inline]
+    pub fn new_hash<T: Any + Hash + Eq>(val: T) -> Box<Dynamic> {
+        Dynamic::from_described(Described {
+            desc: Descriptor::new_hash::<T>(),
+            data: val
+        })
+    }
+
+    /// Create a new, heap-allocated Dynamic value which can be ordered
+    /// relative to, and compared for equality with, other Dynamic values.
+    ///
+    /// See `Descriptor::new_ord`.
+    #[inline]
+    pub fn new_ord<T: Any + Ord>(val: T) -> Box<Dynamic> {
+        Dynamic::from_described(Described {
+            desc: Descriptor::new_ord::<T>(),
             data: val
-        }) as Box<Described<Dyn>>;
+        })
+    }
+
+    /// Create a new, heap-allocated Dynamic value from an existing box.
+    ///
+    /// Since the Dynamic stores a descriptor ahead of the value, the value
+    /// is moved into a new allocation, but it is copied there directly rather
+    /// than through the stack, so large values can be converted safely.
+    #[inline]
+    pub fn from_box<T: Any>(boxed: Box<T>) -> Box<Dynamic> {
+        let mut described = Box::<Described<T>>::new_uninit();
+        let raw = Box::into_raw(boxed);
+
+        unsafe {
+            let ptr = described.as_mut_ptr();
+            ptr::addr_of_mut!((*ptr).desc).write(Descriptor::new::<T>());
+            ptr::copy_nonoverlapping(raw, ptr::addr_of_mut!((*ptr).data), 1);
+
+            // Free the old allocation without dropping the moved value.
+            drop(Box::from_raw(raw as *mut ManuallyDrop<T>));
+
+            Dynamic::from_described_box(described.assume_init())
+        }
+    }
+
+    /// Create a new, heap-allocated Dynamic value from a descriptor and a
+    /// pointer to a boxed value.
+    ///
+    /// The value is moved into a new allocation, which is described by
+    /// `desc`. This is the inverse of `Dynamic::into_raw_parts`.
+    ///
+    /// ## Safety
+    ///
+    /// `data` must point to a valid, uniquely owned value of the type
+    /// described by `desc`, allocated as if by `Box::new`, for instance as
+    /// returned by `Box::into_raw`. Ownership of the value and its allocation
+    /// is transferred to the new Dynamic.
+    #[inline]
+    pub unsafe fn from_raw_parts(desc: Descriptor, data: *mut ()) -> Box<Dynamic> {
+        (desc.rebox)(desc, data)
+    }
+
+    /// Split a Dynamic value into its descriptor and a pointer to the
+    /// contained value, moved into its own allocation.
+    ///
+    /// The pointer is as if returned by `Box::into_raw` for a `Box<T>` of the
+    /// described type, and the caller becomes responsible for it. The value
+    /// can be turned back into a Dynamic with `Dynamic::from_raw_parts`.
+    #[inline]
+    pub fn into_raw_parts(self: Box<Self>) -> (Descriptor, *mut ()) {
+        let desc = self.desc;
+
+        // The unbox function was recorded for the type of the contained
+        // value, which is boxed in a Described of that type.
+        (desc, unsafe { (desc.unbox)(Box::into_raw(self) as *mut ()) })
+    }
 
-        unsafe { mem::transmute(un_sized) }
+    // Erase a described value, moving it to the heap.
+    #[inline]
+    fn from_described<T: Any>(val: Described<T>) -> Box<Dynamic> {
+        Dynamic::from_described_box(Box::new(val))
+    }
+
+    // Erase a boxed, described value.
+    #[inline]
+    fn from_described_box<T: Any>(val: Box<Described<T>>) -> Box<Dynamic> {
+        let un_sized = val as Box<Described<dyn Dyn>>;
+
+        unsafe { Box::from_raw(Box::into_raw(un_sized) as *mut Dynamic) }
     }
 
     /// Create a new, immutable Dynamic value from the given described reference.
@@ -50,8 +286,9 @@ impl Dynamic {
     /// The resulting `Dynamic` can be downcasted back to a `T`.
     #[inline]
     pub fn from_ref<T: Any>(val: &Described<T>) -> &Dynamic {
-        let un_sized = val as &Described<Dyn>;
-        unsafe { mem::transmute(un_sized) }
+        debug_check_described(val);
+        let un_sized = val as &Described<dyn Dyn>;
+        unsafe { &*(un_sized as *const Described<dyn Dyn> as *const Dynamic) }
     }
 
     /// Create a new, mutable Dynamic value from the given described reference.
@@ -59,18 +296,129 @@ impl Dynamic {
     /// The resulting `Dynamic` can be downcasted back to a `T`.
     #[inline]
     pub fn from_mut<T: Any>(val: &mut Described<T>) -> &mut Dynamic {
-        let un_sized = val as &mut Described<Dyn>;
-        unsafe { mem::transmute(un_sized) }
+        debug_check_described(val);
+        let un_sized = val as &mut Described<dyn Dyn>;
+        unsafe { &mut *(un_sized as *mut Described<dyn Dyn> as *mut Dynamic) }
+    }
+
+    /// Create a new, pinned and heap-allocated Dynamic value containing the
+    /// given value.
+    ///
+    /// Equivalent to `Box::into_pin(Dynamic::new(val))`, for values which
+    /// must not be moved once they are erased.
+    #[inline]
+    pub fn pin<T: Any>(val: T) -> Pin<Box<Dynamic>> {
+        Box::into_pin(Dynamic::new(val))
+    }
+
+    /// Create a new, reference-counted Dynamic value containing the given
+    /// value.
+    ///
+    /// The resulting `Rc` can be downcasted back to a `Described<T>` with
+    /// `Dynamic::downcast_rc`.
+    #[inline]
+    pub fn new_rc<T: Any>(val: T) -> Rc<Dynamic> {
+        let un_sized = Rc::new(Described::new(val)) as Rc<Described<dyn Dyn>>;
+
+        unsafe { Rc::from_raw(Rc::into_raw(un_sized) as *const Dynamic) }
+    }
+
+    /// Create a new, atomically reference-counted value containing the
+    /// given value, which can be shared between threads.
+    ///
+    /// Since `Dynamic` is neither `Send` nor `Sync`, an `Arc<Dynamic>`
+    /// could not be shared between threads, so the value is created as a
+    /// `DynamicSendSync`, as by `DynamicSendSync::new_arc`. The `Arc` can be
+    /// downcasted back to a `Described<T>` with `DynamicSendSync::downcast_arc`.
+    ///
+    /// An `Arc<Dynamic>` for use on a single thread can be created from a
+    /// `Box<Dynamic>` with `Arc::from`, and downcasted with
+    /// `Dynamic::downcast_arc`. Weak references to it work as for any other
+    /// `Arc`, through `Arc::downgrade` and `Weak::upgrade`; see also
+    /// `Dynamic::upgrade_downcast`.
+    #[inline]
+    pub fn new_arc<T: Any + Send + Sync>(val: T) -> Arc<DynamicSendSync> {
+        DynamicSendSync::new_arc(val)
     }
 
     /// Read the type id for the contained value.
     #[inline]
-    pub fn id(&self) -> TypeId { self.id }
+    pub fn id(&self) -> TypeId { self.desc.id }
+
+    /// Read the type id for the contained value.
+    ///
+    /// Equivalent to `Dynamic::id`, mirroring `Any::type_id`.
+    #[inline]
+    pub fn type_id(&self) -> TypeId { self.desc.id }
+
+    /// Read the descriptor for the contained value.
+    #[inline]
+    pub fn descriptor(&self) -> Descriptor { self.desc }
+
+    /// Check if the contained type is zero-sized.
+    #[inline]
+    pub fn is_zero_sized(&self) -> bool { self.desc.is_zero_sized() }
+
+    /// Get the memory layout of the contained type.
+    #[inline]
+    pub fn layout(&self) -> Layout { self.desc.layout() }
 
     /// Check if the contained type is a `T`.
     #[inline(always)]
     pub fn is<T: Any>(&self) -> bool {
-        self.id == TypeId::of::<T>()
+        self.is_id(TypeId::of::<T>())
+    }
+
+    /// Check if the contained type has the given type id.
+    ///
+    /// Useful when the expected type is only known at runtime.
+    #[inline(always)]
+    pub fn is_id(&self, id: TypeId) -> bool {
+        self.desc.id == id
+    }
+
+    /// Check if the contained type has the given type token, as returned by
+    /// `type_token`.
+    ///
+    /// The token of the contained type is recorded in its descriptor, as a
+    /// function returning a constant, so this makes one call and compares a
+    /// single word against a token resolved ahead of time. Tokens may
+    /// collide, however, so a match only means the type is *probably* the
+    /// expected one: use `is` or a checked downcast where correctness
+    /// depends on it.
+    #[inline(always)]
+    pub fn is_token(&self, token: u64) -> bool {
+        (self.desc.type_hash)() == token
+    }
+
+    /// Get a pointer to the contained value, excluding its descriptor.
+    ///
+    /// The pointer is only valid for as long as `self` is borrowed, and
+    /// reading through it requires knowing the real type of the value.
+    #[inline]
+    pub fn as_ptr(&self) -> *const () {
+        &self.data as *const dyn Dyn as *const ()
+    }
+
+    /// Get a mutable pointer to the contained value, excluding its
+    /// descriptor.
+    ///
+    /// The pointer is only valid for as long as `self` is borrowed, and
+    /// reading or writing through it requires knowing the real type of the
+    /// value.
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut () {
+        &mut self.data as *mut dyn Dyn as *mut ()
+    }
+
+    /// Clone the contained value into a new Dynamic.
+    ///
+    /// Returns `None` if the value was not created with cloning support, for
+    /// instance through `Dynamic::new_cloneable`.
+    #[inline]
+    pub fn try_clone(&self) -> Option<Box<Dynamic>> {
+        // The clone function creates a boxed value of the described type.
+        self.desc.clone.map(|clone| unsafe { Dynamic::from_raw_parts(self.desc, clone(self.as_ptr())) })
     }
 
     /// If the contained value is a `T`, downcast back to it.
@@ -85,102 +433,2610 @@ impl Dynamic {
         }
     }
 
-    /// If the contained value is a `T`, get an immutable reference to it.
+    /// If the contained value is a `T`, downcast back to it.
+    ///
+    /// Like `Dynamic::downcast`, but if the value is not a `T`, the error
+    /// describes both the expected and the actual type, and holds the
+    /// original Dynamic.
     #[inline]
-    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+    pub fn try_downcast<T: Any>(self: Box<Self>) -> Result<Box<Described<T>>, DowncastError> {
+        self.downcast().map_err(|value| DowncastError {
+            expected: TypeId::of::<T>(),
+            expected_name: any::type_name::<T>(),
+            value
+        })
+    }
+
+    /// If the contained value is a `T`, downcast a shared Dynamic back to it.
+    ///
+    /// The reference count is preserved, so other clones of the `Rc` remain
+    /// valid and observe the same value. If the value is not a `T`, returns
+    /// `Err(self)`.
+    #[inline]
+    pub fn downcast_rc<T: Any>(self: Rc<Self>) -> Result<Rc<Described<T>>, Rc<Self>> {
         if self.is::<T>() {
-            Some(unsafe { self.data.downcast_ref_unchecked() })
+            Ok(unsafe { Rc::from_raw(Rc::into_raw(self) as *const Described<T>) })
         } else {
-            None
+            Err(self)
         }
     }
 
-    /// If the contained value is a `T`, get a mutable reference to it.
+    /// If the contained value is a `T`, downcast an atomically shared
+    /// Dynamic back to it.
+    ///
+    /// The reference count is preserved, so other clones of the `Arc` remain
+    /// valid and observe the same value. If the value is not a `T`, returns
+    /// `Err(self)`.
     #[inline]
-    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+    pub fn downcast_arc<T: Any>(self: Arc<Self>) -> Result<Arc<Described<T>>, Arc<Self>> {
         if self.is::<T>() {
-            Some(unsafe { self.data.downcast_mut_unchecked() })
+            Ok(unsafe { Arc::from_raw(Arc::into_raw(self) as *const Described<T>) })
         } else {
-            None
+            Err(self)
         }
     }
-}
 
-impl fmt::Debug for Dynamic {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("Dynamic")
-            .field("id", &self.id)
-            .field("data", &"{{ dynamically typed value }}")
-            .finish()
+    /// Upgrade a weak reference to a shared Dynamic and downcast it to a `T`.
+    ///
+    /// Returns `None` if the value has already been dropped, or if the
+    /// contained value is not a `T`. The weak reference is left untouched.
+    #[inline]
+    pub fn upgrade_downcast<T: Any>(weak: &Weak<Dynamic>) -> Option<Arc<Described<T>>> {
+        weak.upgrade().and_then(|arc| arc.downcast_arc().ok())
     }
-}
 
-/// A value T paired with its type descriptor.
-///
-/// Can be converted to a `Dynamic` value.
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub struct Described<T: ?Sized> {
-    // The TypeId is private to prevent mutation, as a user could then
-    // invalidate it.
-    id: TypeId,
+    /// If the contained value is a `T`, move it into a new `Box<T>`.
+    ///
+    /// Since the Dynamic stores a descriptor ahead of the value, the value
+    /// is moved into a new allocation, but it is copied there directly rather
+    /// than through the stack. If the value is not a `T`, returns
+    /// `Err(self)`.
+    #[inline]
+    pub fn downcast_into_box<T: Any>(self: Box<Self>) -> Result<Box<T>, Box<Self>> {
+        let described = self.downcast::<T>()?;
+        let mut boxed = Box::<T>::new_uninit();
+        let raw = Box::into_raw(described);
 
-    /// The described data.
-    pub data: T
-}
+        unsafe {
+            ptr::copy_nonoverlapping(ptr::addr_of!((*raw).data), boxed.as_mut_ptr(), 1);
 
-impl<T: Any> Described<T> {
-    /// Create a new Described instance that can be converted to a Dynamic.
+            // Free the old allocation without dropping the moved value.
+            drop(Box::from_raw(raw as *mut Described<ManuallyDrop<T>>));
+
+            Ok(boxed.assume_init())
+        }
+    }
+
+    /// If the contained value is a `T`, move it out of the Dynamic.
+    ///
+    /// If the value is not a `T`, returns `Err(self)`.
     #[inline]
-    pub fn new(val: T) -> Described<T> {
-        Described {
-            id: TypeId::of::<T>(),
-            data: val
+    pub fn into_inner<T: Any>(self: Box<Self>) -> Result<T, Box<Self>> {
+        self.downcast::<T>().map(|described| described.data)
+    }
+
+    /// If the contained value is a `T`, transform it into a new Dynamic
+    /// value using `f`.
+    ///
+    /// If the value is not a `T`, returns `Err(self)`.
+    #[inline]
+    pub fn map<T, U, F>(self: Box<Self>, f: F) -> Result<Box<Dynamic>, Box<Self>>
+    where T: Any, U: Any, F: FnOnce(T) -> U {
+        self.into_inner::<T>().map(|val| Dynamic::new(f(val)))
+    }
+
+    /// If the contained value is a `T`, try to transform it into a new
+    /// Dynamic value using `f`.
+    ///
+    /// If the value is not a `T`, returns `Err(TryMapError::WrongType(self))`.
+    /// If `f` fails, returns its error as `Err(TryMapError::Failed(err))`.
+    #[inline]
+    pub fn try_map<T, U, E, F>(self: Box<Self>, f: F) -> Result<Box<Dynamic>, TryMapError<E>>
+    where T: Any, U: Any, F: FnOnce(T) -> Result<U, E> {
+        match self.into_inner::<T>() {
+            Ok(val) => f(val).map(Dynamic::new).map_err(TryMapError::Failed),
+            Err(this) => Err(TryMapError::WrongType(this))
         }
     }
 
-    /// Read the type id for this value.
+    /// If the contained value is a `T`, get an immutable reference to it.
     #[inline]
-    pub fn id(&self) -> TypeId { self.id }
-}
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        if self.is::<T>() {
+            Some(unsafe { self.downcast_ref_unchecked() })
+        } else {
+            None
+        }
+    }
 
-// Empty trait for small vtables.
-trait Dyn {}
-impl<T> Dyn for T {}
+    /// If the contained value is a `T`, replace it with `val`, returning the
+    /// old value.
+    ///
+    /// If the value is not a `T`, returns `Err(val)`.
+    #[inline]
+    pub fn replace<T: Any>(&mut self, val: T) -> Result<T, T> {
+        match self.downcast_mut::<T>() {
+            Some(old) => Ok(mem::replace(old, val)),
+            None => Err(val)
+        }
+    }
 
-// Add raw downcasting methods to Dyn trait objects.
-unsafe impl UnsafeAnyExt for Dyn {}
+    /// View the raw bytes of the contained value.
+    ///
+    /// ## Safety
+    ///
+    /// Every byte of the contained value must be initialized, so its type
+    /// must not contain padding, uninitialized memory or pointer provenance
+    /// the caller relies on. In practice this is only meaningful for plain
+    /// `#[repr(C)]` types without padding. Note that reading padding bytes is
+    /// undefined behavior, not merely nondeterministic.
+    #[inline]
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        slice::from_raw_parts(self.as_ptr() as *const u8, self.desc.size)
+    }
 
-#[cfg(test)]
-mod test {
-    use std::any::TypeId;
-    use {Dynamic, Described};
+    /// If both values have the same type, swap them and return `true`.
+    ///
+    /// If the types differ, neither value is changed and `false` is returned.
+    #[inline]
+    pub fn swap(&mut self, other: &mut Dynamic) -> bool {
+        if self.desc.id != other.desc.id { return false }
 
-    struct X(usize);
-    struct Y(usize);
-    struct Z(usize);
+        // The size is read from the vtable of the value itself rather than
+        // the descriptor. Both values have the same type, so they have the
+        // same size, and they cannot overlap since they are borrowed mutably.
+        let size = mem::size_of_val(&self.data);
+        debug_assert_eq!(size, self.desc.size, "the descriptor of `{}` has the wrong size",
+                         self.desc.name());
 
-    #[test]
-    fn test_downcasting() {
-        let mut x = Dynamic::new(X(1));
+        unsafe {
+            ptr::swap_nonoverlapping(self.as_mut_ptr() as *mut u8,
+                                     other.as_mut_ptr() as *mut u8,
+                                     size);
+        }
 
-        assert!(x.is::<X>());
-        assert!(!x.is::<Y>());
-        assert!(!x.is::<Z>());
+        true
+    }
 
-        *x.downcast_mut::<X>().unwrap() = X(100);
-        assert_eq!(x.downcast_ref::<X>().unwrap().0, 100);
+    /// View the contained value as a std `Any` trait object.
+    ///
+    /// Downcasting the result behaves exactly like downcasting the Dynamic.
+    #[inline]
+    pub fn as_any(&self) -> &dyn Any {
+        // The vtable was recorded for the type of the contained value.
+        unsafe { &*(self.desc.any)(self.as_ptr() as *mut ()) }
+    }
 
-        let described_x = x.downcast::<X>().unwrap();
-        assert_eq!(described_x.id(), TypeId::of::<X>());
-        assert_eq!(described_x.data.0, 100);
+    /// View the contained value as a mutable std `Any` trait object.
+    ///
+    /// Downcasting the result behaves exactly like downcasting the Dynamic.
+    #[inline]
+    pub fn as_any_mut(&mut self) -> &mut dyn Any {
+        // The vtable was recorded for the type of the contained value.
+        unsafe { &mut *(self.desc.any)(self.as_mut_ptr()) }
     }
 
-    #[test]
-    fn test_dynamic_refs() {
-        let described_z = Described::new(Z(1000));
+    /// Move the contained value into a std `Any` trait object.
+    ///
+    /// The value is moved into a new allocation, since a `Box<dyn Any>` must
+    /// point directly at the value without its descriptor.
+    #[inline]
+    pub fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        let (desc, data) = self.into_raw_parts();
 
-        let z_ref = Dynamic::from_ref(&described_z);
-        assert_eq!(z_ref.downcast_ref::<Z>().unwrap().0, 1000);
+        // The data is a boxed value of the type the vtable was recorded for.
+        unsafe { Box::from_raw((desc.any)(data)) }
+    }
+
+    /// View the contained value as a std `Error` trait object, if the Dynamic
+    /// was created with error support, by `Dynamic::new_error`.
+    ///
+    /// The result can be downcast with `<dyn Error>::downcast_ref`, and its
+    /// `source` is that of the contained value.
+    #[inline]
+    pub fn as_error(&self) -> Option<&(dyn Error + 'static)> {
+        // The vtable was recorded for the type of the contained value.
+        self.desc.error.map(|error| unsafe { &*error(self.as_ptr() as *mut ()) })
+    }
+
+    /// Move the contained value into a std `Error` trait object, if the
+    /// Dynamic was created with error support, by `Dynamic::new_error`.
+    ///
+    /// If the value does not support errors, returns `Err(self)`. Otherwise
+    /// the value is moved into a new allocation, as in `Dynamic::into_any`.
+    #[inline]
+    pub fn into_error(self: Box<Self>) -> Result<Box<dyn Error>, Box<Dynamic>> {
+        match self.desc.error {
+            Some(error) => {
+                let (_, data) = self.into_raw_parts();
+
+                // The data is a boxed value of the type the vtable was
+                // recorded for.
+                Ok(unsafe { Box::from_raw(error(data)) })
+            },
+            None => Err(self)
+        }
+    }
+
+    /// If the given std `Any` trait object contains a `T`, move it into a new
+    /// Dynamic value.
+    ///
+    /// If the value is not a `T`, returns `Err(val)`. See also the `From`
+    /// conversion, which does not require knowing the type.
+    #[inline]
+    pub fn from_any<T: Any>(val: Box<dyn Any>) -> Result<Box<Dynamic>, Box<dyn Any>> {
+        val.downcast::<T>().map(|val| Dynamic::new(*val))
+    }
+
+    /// If the contained type has the given type id, get a pointer to the
+    /// contained value.
+    ///
+    /// This is the runtime counterpart to `downcast_ref`, for building
+    /// reflection layers which only know types by their `TypeId`. The caller
+    /// is responsible for interpreting the pointer as the right type, and it
+    /// is only valid for as long as `self` is borrowed.
+    #[inline]
+    pub fn downcast_ptr_by_id(&self, id: TypeId) -> Option<*const ()> {
+        if self.is_id(id) {
+            Some(self.as_ptr())
+        } else {
+            None
+        }
+    }
+
+    /// Get an immutable reference to the contained value as a `T`, without
+    /// checking its type.
+    ///
+    /// ## Safety
+    ///
+    /// The contained value must be a `T`, for instance as previously checked
+    /// using `is::<T>()`.
+    #[inline]
+    pub unsafe fn downcast_ref_unchecked<T: Any>(&self) -> &T {
+        &*(&self.data as *const dyn Dyn as *const T)
+    }
+
+    /// Get a mutable reference to the contained value as a `T`, without
+    /// checking its type.
+    ///
+    /// ## Safety
+    ///
+    /// The contained value must be a `T`, for instance as previously checked
+    /// using `is::<T>()`.
+    #[inline]
+    pub unsafe fn downcast_mut_unchecked<T: Any>(&mut self) -> &mut T {
+        &mut *(&mut self.data as *mut dyn Dyn as *mut T)
+    }
+
+    /// If the contained value is a `T`, get an immutable reference to it,
+    /// otherwise return `default`.
+    #[inline]
+    pub fn downcast_ref_or<'a, T: Any>(&'a self, default: &'a T) -> &'a T {
+        self.downcast_ref::<T>().unwrap_or(default)
+    }
+
+    /// If the contained value is a `T`, get an immutable reference to it,
+    /// otherwise return the result of calling `default`.
+    #[inline]
+    pub fn downcast_ref_or_else<'a, T, F>(&'a self, default: F) -> &'a T
+    where T: Any, F: FnOnce() -> &'a T {
+        self.downcast_ref::<T>().unwrap_or_else(default)
+    }
+
+    /// If the contained value is a `T`, get a mutable reference to it.
+    #[inline]
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        if self.is::<T>() {
+            Some(unsafe { self.downcast_mut_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    /// If the contained value is a `T`, get a pinned mutable reference to it.
+    ///
+    /// `Dynamic` is never `Unpin`, so a pinned Dynamic can only be accessed
+    /// through this method, which keeps the contained value pinned.
+    #[inline]
+    pub fn downcast_pin_mut<T: Any>(self: Pin<&mut Self>) -> Option<Pin<&mut T>> {
+        // The contained value is never moved out of the pinned Dynamic.
+        unsafe { self.get_unchecked_mut().downcast_mut().map(|val| Pin::new_unchecked(val)) }
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl Dynamic {
+    /// Create a new Dynamic value containing the given value, allocated in
+    /// the given allocator.
+    ///
+    /// The resulting `Dynamic` can be downcasted back to a `T` with
+    /// `Dynamic::downcast_in`.
+    #[inline]
+    pub fn new_in<T: Any, A: Allocator>(val: T, alloc: A) -> Box<Dynamic, A> {
+        let un_sized = Box::new_in(Described::new(val), alloc) as Box<Described<dyn Dyn>, A>;
+        let (raw, alloc) = Box::into_raw_with_allocator(un_sized);
+
+        unsafe { Box::from_raw_in(raw as *mut Dynamic, alloc) }
+    }
+
+    /// If the contained value is a `T`, downcast a Dynamic allocated in any
+    /// allocator back to it.
+    ///
+    /// The allocator is kept, so the value is still freed through it. If the
+    /// value is not a `T`, returns `Err(this)`.
+    #[inline]
+    pub fn downcast_in<T: Any, A: Allocator>(this: Box<Self, A>)
+            -> Result<Box<Described<T>, A>, Box<Self, A>> {
+        if this.is::<T>() {
+            let (raw, alloc) = Box::into_raw_with_allocator(this);
+            Ok(unsafe { Box::from_raw_in(raw as *mut Described<T>, alloc) })
+        } else {
+            Err(this)
+        }
+    }
+}
+
+/// The error returned by `Dynamic::try_map`.
+#[derive(Debug)]
+pub enum TryMapError<E> {
+    /// The Dynamic did not contain a value of the expected type, and is
+    /// returned unchanged.
+    WrongType(Box<Dynamic>),
+
+    /// The transformation failed with the given error.
+    Failed(E)
+}
+
+impl<E: fmt::Display> fmt::Display for TryMapError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TryMapError::WrongType(ref val) =>
+                write!(f, "Dynamic value of type `{}` had the wrong type", val.desc.name()),
+            TryMapError::Failed(ref err) => write!(f, "transformation failed: {}", err)
+        }
+    }
+}
+
+impl<E: Error> Error for TryMapError<E> {}
+
+/// The error returned by `Dynamic::try_downcast`, and by the `TryFrom`
+/// conversion to `Box<Described<T>>`.
+///
+/// Records the type which was expected and holds the original Dynamic,
+/// whose descriptor describes the type which was found. The `Display` output
+/// names both types, for instance "expected a `u8`, found a `i32`".
+#[derive(Debug)]
+pub struct DowncastError {
+    expected: TypeId,
+    expected_name: &'static str,
+    value: Box<Dynamic>
+}
+
+impl DowncastError {
+    /// Get the id of the type which was expected.
+    #[inline]
+    pub fn expected(&self) -> TypeId { self.expected }
+
+    /// Get the name of the type which was expected.
+    ///
+    /// As with `Descriptor::name`, the name is intended for diagnostics only.
+    #[inline]
+    pub fn expected_name(&self) -> &'static str { self.expected_name }
+
+    /// Get the descriptor of the type which was found.
+    #[inline]
+    pub fn actual(&self) -> Descriptor { self.value.desc }
+
+    /// Recover the original Dynamic.
+    #[inline]
+    pub fn into_inner(self) -> Box<Dynamic> { self.value }
+}
+
+impl fmt::Display for DowncastError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a `{}`, found a `{}`", self.expected_name, self.value.desc.name())
+    }
+}
+
+impl Error for DowncastError {}
+
+impl From<DowncastError> for Box<Dynamic> {
+    #[inline]
+    fn from(err: DowncastError) -> Box<Dynamic> { err.value }
+}
+
+/// Shows the descriptor and, if the Dynamic was created with `Debug` support
+/// (e.g. by `Dynamic::new_debug`), the contained value. Otherwise a
+/// placeholder is shown in place of the value.
+impl fmt::Debug for Dynamic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("Dynamic");
+        debug.field("descriptor", &self.desc);
+
+        match self.desc.debug {
+            Some(fmt) => debug.field("data", &DebugData(fmt, self.as_ptr())),
+            None => debug.field("data", &"{{ dynamically typed value }}")
+        };
+
+        debug.finish()
+    }
+}
+
+/// Formats the contained value, if the Dynamic was created with `Display`
+/// support (e.g. by `Dynamic::new_display`).
+///
+/// Formatting a value without `Display` support returns `Err(fmt::Error)`.
+impl fmt::Display for Dynamic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.desc.display {
+            // The function was recorded for the type of the contained value.
+            Some(fmt) => unsafe { fmt(self.as_ptr(), f) },
+            None => Err(fmt::Error)
+        }
+    }
+}
+
+/// Compares the contained values, if both Dynamics were created with equality
+/// support, by `Dynamic::new_eq`, `Dynamic::new_hash` or `Dynamic::new_ord`.
+///
+/// Values of different types are never equal, whether or not they support
+/// equality.
+///
+/// ## Panics
+///
+/// Panics if the values have the same type, but either was created without
+/// equality support.
+impl PartialEq for Dynamic {
+    fn eq(&self, other: &Dynamic) -> bool {
+        if self.desc.id != other.desc.id { return false }
+
+        match (self.desc.eq, other.desc.eq) {
+            // Both values are of the type the function was recorded for.
+            (Some(eq), Some(_)) => unsafe { eq(self.as_ptr(), other.as_ptr()) },
+            _ => panic!("Dynamic values of type `{}` were compared, but not all \
+                         were created with equality support", self.desc.name())
+        }
+    }
+}
+
+/// Wraps a std `Any` trait object in a Dynamic value.
+///
+/// Since the concrete type of the trait object is unknown, its descriptor
+/// cannot be computed, so the resulting Dynamic contains the `Box<dyn Any>`
+/// itself and is described as such. To reach the value, downcast to
+/// `Box<dyn Any>` first:
+///
+/// ```
+/// use dynamic::Dynamic;
+/// use std::any::Any;
+///
+/// let any = Box::new(5i32) as Box<dyn Any>;
+/// let dynamic = Box::<Dynamic>::from(any);
+///
+/// assert!(!dynamic.is::<i32>());
+/// let any = dynamic.downcast_ref::<Box<dyn Any>>().unwrap();
+/// assert_eq!(any.downcast_ref::<i32>(), Some(&5));
+/// ```
+///
+/// When the concrete type is known, `Dynamic::from_any` produces a Dynamic
+/// containing the value directly.
+impl From<Box<dyn Any>> for Box<Dynamic> {
+    #[inline]
+    fn from(val: Box<dyn Any>) -> Box<Dynamic> { Dynamic::new(val) }
+}
+
+/// Erases the type of a boxed, described value.
+///
+/// This reuses the allocation and the existing descriptor, so it is only a
+/// pointer cast.
+impl<T: Any> From<Box<Described<T>>> for Box<Dynamic> {
+    #[inline]
+    fn from(val: Box<Described<T>>) -> Box<Dynamic> { Dynamic::from_described_box(val) }
+}
+
+/// Downcasts a Dynamic to a boxed, described `T`.
+///
+/// Equivalent to `Dynamic::try_downcast`: if the value is not a `T`, the
+/// error holds the original Dynamic.
+impl<T: Any> TryFrom<Box<Dynamic>> for Box<Described<T>> {
+    type Error = DowncastError;
+
+    #[inline]
+    fn try_from(val: Box<Dynamic>) -> Result<Box<Described<T>>, DowncastError> { val.try_downcast() }
+}
+
+/// Dereferences to the contained value as a std `Any` trait object.
+///
+/// Equivalent to `Dynamic::as_any`. Note that methods of `Dynamic` take
+/// precedence over those of `Any` with the same name, such as `is` and
+/// `downcast_ref`, though they behave the same.
+impl Deref for Dynamic {
+    type Target = dyn Any;
+
+    #[inline]
+    fn deref(&self) -> &dyn Any { self.as_any() }
+}
+
+/// Views the contained value as a std `Any` trait object.
+///
+/// Equivalent to `Dynamic::as_any`, for use with APIs taking
+/// `impl AsRef<dyn Any>`.
+impl AsRef<dyn Any> for Dynamic {
+    #[inline]
+    fn as_ref(&self) -> &dyn Any { self.as_any() }
+}
+
+/// Borrows the contained value as a std `Any` trait object.
+///
+/// Equivalent to `Dynamic::as_any`, for use with APIs generic over
+/// `Borrow<dyn Any>`.
+impl Borrow<dyn Any> for Box<Dynamic> {
+    #[inline]
+    fn borrow(&self) -> &dyn Any { self.as_any() }
+}
+
+/// Mutably borrows the contained value as a std `Any` trait object.
+///
+/// Equivalent to `Dynamic::as_any_mut`.
+impl BorrowMut<dyn Any> for Box<Dynamic> {
+    #[inline]
+    fn borrow_mut(&mut self) -> &mut dyn Any { self.as_any_mut() }
+}
+
+/// Clones the contained value, if the Dynamic was created with cloning
+/// support (e.g. by `Dynamic::new_cloneable`).
+///
+/// The clone is described by the same descriptor as the original.
+///
+/// ## Panics
+///
+/// Panics if the value was created without cloning support.
+impl Clone for Box<Dynamic> {
+    fn clone(&self) -> Box<Dynamic> {
+        match self.try_clone() {
+            Some(cloned) => cloned,
+            None => panic!("a Dynamic value of type `{}` was cloned, but was not \
+                            created with cloning support", self.desc.name())
+        }
+    }
+}
+
+/// Compares the contained value to a concrete value.
+///
+/// This does not require equality support, since the type of the value is
+/// known at the comparison site. A Dynamic never equals a value of a type
+/// other than the contained type.
+impl<T: Any + PartialEq> PartialEq<T> for Dynamic {
+    #[inline]
+    fn eq(&self, other: &T) -> bool {
+        self.downcast_ref::<T>() == Some(other)
+    }
+}
+
+/// Dynamic values are `Eq`, so they can be used as keys in collections,
+/// since equality support can only be recorded for types which are `Eq`.
+impl Eq for Dynamic {}
+
+/// Hashes the type and contained value, if the Dynamic was created with
+/// hashing support (e.g. by `Dynamic::new_hash`).
+///
+/// The hash is consistent with `PartialEq`, so values which are equal have
+/// equal hashes.
+///
+/// ## Panics
+///
+/// Panics if the value was created without hashing support.
+impl Hash for Dynamic {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.desc.type_hash());
+
+        match self.desc.hash {
+            // The function was recorded for the type of the contained value.
+            Some(hash) => unsafe { hash(self.as_ptr(), state) },
+            None => panic!("a Dynamic value of type `{}` was hashed, but was not \
+                            created with hashing support", self.desc.name())
+        }
+    }
+}
+
+impl PartialOrd for Dynamic {
+    #[inline]
+    fn partial_cmp(&self, other: &Dynamic) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders values by their type first, using the order of their descriptors,
+/// then by the contained values if both Dynamics were created with ordering
+/// support (e.g. by `Dynamic::new_ord`).
+///
+/// This groups values of the same type together, in order.
+///
+/// ## Panics
+///
+/// Panics if the values have the same type, but either was created without
+/// ordering support.
+impl Ord for Dynamic {
+    fn cmp(&self, other: &Dynamic) -> Ordering {
+        match self.desc.cmp(&other.desc) {
+            Ordering::Equal => (),
+            unequal => return unequal
+        }
+
+        match (self.desc.cmp, other.desc.cmp) {
+            // Both values are of the type the function was recorded for.
+            (Some(cmp), Some(_)) => unsafe { cmp(self.as_ptr(), other.as_ptr()) },
+            _ => panic!("Dynamic values of type `{}` were ordered, but not all \
+                         were created with ordering support", self.desc.name())
+        }
+    }
+}
+
+// Formats the data of a Dynamic using the Debug function in its Descriptor.
+struct DebugData(unsafe fn(*const (), &mut fmt::Formatter) -> fmt::Result, *const ());
+
+impl fmt::Debug for DebugData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The function and data were read from the same Dynamic.
+        unsafe { (self.0)(self.1, f) }
+    }
+}
+
+/// A value T paired with its type descriptor.
+///
+/// Can be converted to a `Dynamic` value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Described<T: ?Sized> {
+    // The Descriptor is private to prevent mutation, as a user could then
+    // invalidate it.
+    desc: Descriptor,
+
+    /// The described data.
+    pub data: T
+}
+
+impl<T: Any> Described<T> {
+    /// Create a new Described instance that can be converted to a Dynamic.
+    #[inline]
+    pub fn new(val: T) -> Described<T> {
+        Described {
+            desc: Descriptor::new::<T>(),
+            data: val
+        }
+    }
+
+    /// Create a new Described instance from a value and an existing
+    /// descriptor.
+    ///
+    /// This allows attaching descriptors which were not created by
+    /// `Descriptor::new`, for instance ones recording optional support.
+    ///
+    /// ## Safety
+    ///
+    /// `desc` must accurately describe `T`. In particular, it must have been
+    /// created for `T` by one of the `Descriptor` constructors, or copied
+    /// from a descriptor which was.
+    #[inline]
+    pub unsafe fn from_parts(desc: Descriptor, data: T) -> Described<T> {
+        debug_assert!(desc.id == TypeId::of::<T>(), "the descriptor for `{}` does not describe `{}`",
+                      desc.name(), any::type_name::<T>());
+
+        Described { desc, data }
+    }
+
+    /// Read the type id for this value.
+    #[inline]
+    pub fn id(&self) -> TypeId { self.desc.id }
+
+    /// Read the descriptor for this value.
+    #[inline]
+    pub fn descriptor(&self) -> Descriptor { self.desc }
+
+    /// Erase the type of this value, borrowing it as a Dynamic.
+    ///
+    /// Equivalent to `Dynamic::from_ref`.
+    #[inline]
+    pub fn as_dynamic(&self) -> &Dynamic { Dynamic::from_ref(self) }
+
+    /// Erase the type of this value, mutably borrowing it as a Dynamic.
+    ///
+    /// Equivalent to `Dynamic::from_mut`.
+    #[inline]
+    pub fn as_dynamic_mut(&mut self) -> &mut Dynamic { Dynamic::from_mut(self) }
+
+    /// Erase the type of this value, moving it into a new Dynamic.
+    ///
+    /// Unlike `Dynamic::new`, this keeps the existing descriptor, including
+    /// any optional support it records, such as for cloning.
+    #[inline]
+    pub fn into_dynamic(self) -> Box<Dynamic> { Dynamic::from_described(self) }
+
+    /// Unwrap the described value.
+    #[inline]
+    pub fn into_inner(self) -> T { self.data }
+
+    /// Transform the described value, describing the result.
+    ///
+    /// The descriptor of the result is created for `U`, since the original
+    /// descriptor describes `T` and would be invalid for the new value.
+    #[inline]
+    pub fn map<U: Any, F: FnOnce(T) -> U>(self, f: F) -> Described<U> {
+        Described::new(f(self.data))
+    }
+}
+
+/// Describes a value, equivalent to `Described::new`.
+impl<T: Any> From<T> for Described<T> {
+    #[inline]
+    fn from(val: T) -> Described<T> { Described::new(val) }
+}
+
+/// Describes the default value of `T`.
+impl<T: Any + Default> Default for Described<T> {
+    #[inline]
+    fn default() -> Described<T> { Described::new(T::default()) }
+}
+
+/// Hashes the described value alone, consistent with its `Borrow`
+/// implementation.
+impl<T: ?Sized + Hash> Hash for Described<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) { self.data.hash(state) }
+}
+
+impl<T: ?Sized> AsRef<T> for Described<T> {
+    #[inline]
+    fn as_ref(&self) -> &T { &self.data }
+}
+
+/// Borrows the described value, so `Described<T>` keys can be looked up by
+/// `T` in maps and sets.
+impl<T: ?Sized> Borrow<T> for Described<T> {
+    #[inline]
+    fn borrow(&self) -> &T { &self.data }
+}
+
+/// Dereferences to the described value.
+///
+/// Methods of `Described` itself, such as `map`, take precedence over
+/// methods of the value with the same name.
+impl<T: ?Sized> Deref for Described<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T { &self.data }
+}
+
+/// Mutably dereferences to the described value.
+///
+/// Mutating the value cannot change its type, so the descriptor remains
+/// valid.
+impl<T: ?Sized> DerefMut for Described<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T { &mut self.data }
+}
+
+/// A description of a type, computed once at creation-time.
+///
+/// Queries against a `Descriptor` are plain field reads, so no virtual
+/// calls are needed to answer them.
+///
+/// Descriptors can only be created by the constructors in this crate, each
+/// of which describes the type it is given, so the size, alignment and
+/// functions of a descriptor always agree with its `id`.
+///
+/// Descriptors are compared, ordered and hashed by their `id` alone, since it
+/// uniquely identifies the described type. The ordering is arbitrary but
+/// stable for the duration of a single program run; it is not meaningful
+/// across builds.
+///
+/// The layout is `#[repr(C)]` and begins with the `size` and `alignment`
+/// fields, as two `usize`s (`size_t` in C), so foreign code can read them
+/// directly. The remaining fields, including the `TypeId`, have no stable
+/// representation and must be treated as opaque. Foreign code which only
+/// needs the layout of the type should prefer `Descriptor::raw_layout`.
+///
+/// A descriptor can also carry user-defined metadata of type `M`, computed
+/// when the descriptor is created (see `Descriptor::with_meta`). Descriptors
+/// with the default metadata, `()`, are used by `Dynamic` and `Described`.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Descriptor<M = ()> {
+    // Private, so that they cannot disagree with the functions below.
+    size: usize,
+
+    alignment: usize,
+
+    id: TypeId,
+
+    // Diagnostic only, see `Descriptor::name`.
+    //
+    // Stored as a function since `type_name` cannot be called in const
+    // contexts.
+    name: fn() -> &'static str,
+
+    // See `Descriptor::type_hash`. Stored as a function for the same reason
+    // as the name, since hashing a `TypeId` cannot be done in const contexts
+    // either.
+    type_hash: fn() -> u64,
+
+    needs_drop: bool,
+
+    drop: unsafe fn(*mut ()),
+
+    clone: Option<unsafe fn(*const ()) -> *mut ()>,
+
+    debug: Option<unsafe fn(*const (), &mut fmt::Formatter) -> fmt::Result>,
+
+    display: Option<unsafe fn(*const (), &mut fmt::Formatter) -> fmt::Result>,
+
+    eq: Option<unsafe fn(*const (), *const ()) -> bool>,
+
+    hash: Option<unsafe fn(*const (), &mut dyn Hasher)>,
+
+    cmp: Option<unsafe fn(*const (), *const ()) -> Ordering>,
+
+    default: Option<fn() -> *mut ()>,
+
+    // Moves a boxed value of the described type into a new Dynamic.
+    rebox: unsafe fn(Descriptor, *mut ()) -> Box<Dynamic>,
+
+    // Moves the value out of a boxed Described of the described type, into
+    // a new box.
+    unbox: unsafe fn(*mut ()) -> *mut (),
+
+    // Attaches the Any vtable of the described type to a pointer.
+    any: fn(*mut ()) -> *mut dyn Any,
+
+    // Attaches the Error vtable of the described type to a pointer, if the
+    // type is known to implement Error.
+    error: Option<fn(*mut ()) -> *mut dyn Error>,
+
+    meta: M
+}
+
+impl Descriptor {
+    /// Create a new Descriptor describing the type `T`.
+    ///
+    /// This is a `const fn`, so descriptors can be computed at compile time:
+    ///
+    /// ```
+    /// use dynamic::Descriptor;
+    ///
+    /// const STRING: Descriptor = Descriptor::new::<String>();
+    /// assert_eq!(STRING, Descriptor::new::<String>());
+    /// ```
+    #[inline]
+    pub const fn new<T: Any>() -> Descriptor {
+        Descriptor {
+            size: mem::size_of::<T>(),
+            alignment: mem::align_of::<T>(),
+            id: TypeId::of::<T>(),
+            name: any::type_name::<T>,
+            type_hash: type_hash::<T>,
+            needs_drop: mem::needs_drop::<T>(),
+            drop: drop_value::<T>,
+            clone: None,
+            debug: None,
+            display: None,
+            eq: None,
+            hash: None,
+            cmp: None,
+            default: None,
+            rebox: rebox_value::<T>,
+            unbox: unbox_value::<T>,
+            any: any_value::<T>,
+            error: None,
+            meta: ()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to clone values of `T`.
+    ///
+    /// See `Descriptor::clone_fn`.
+    #[inline]
+    pub const fn new_cloneable<T: Any + Clone>() -> Descriptor {
+        Descriptor {
+            clone: Some(clone_value::<T>),
+            ..Descriptor::new::<T>()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to format values of `T` using `Debug`.
+    ///
+    /// See `Descriptor::debug_fn`.
+    #[inline]
+    pub const fn new_debug<T: Any + fmt::Debug>() -> Descriptor {
+        Descriptor {
+            debug: Some(debug_value::<T>),
+            ..Descriptor::new::<T>()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to format values of `T` using `Display`.
+    ///
+    /// See `Descriptor::display_fn`.
+    #[inline]
+    pub const fn new_display<T: Any + fmt::Display>() -> Descriptor {
+        Descriptor {
+            display: Some(display_value::<T>),
+            ..Descriptor::new::<T>()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to view values of `T` as std `Error` trait objects.
+    ///
+    /// Since `Error` implies `Debug` and `Display`, the descriptor also
+    /// records how to format values of `T` with both.
+    ///
+    /// See `Dynamic::as_error`.
+    #[inline]
+    pub const fn new_error<T: Any + Error>() -> Descriptor {
+        Descriptor {
+            debug: Some(debug_value::<T>),
+            display: Some(display_value::<T>),
+            error: Some(error_value::<T>),
+            ..Descriptor::new::<T>()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to compare values of `T` for equality.
+    ///
+    /// `T` must be `Eq`, not just `PartialEq`, since `Dynamic` is `Eq`. See
+    /// `Descriptor::eq_fn`.
+    #[inline]
+    pub const fn new_eq<T: Any + Eq>() -> Descriptor {
+        Descriptor {
+            eq: Some(eq_values::<T>),
+            ..Descriptor::new::<T>()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to hash values of `T`.
+    ///
+    /// Since hashing is only useful alongside equality, the descriptor also
+    /// records how to compare values of `T`, as in `Descriptor::new_eq`.
+    ///
+    /// See `Descriptor::hash_fn`.
+    #[inline]
+    pub const fn new_hash<T: Any + Hash + Eq>() -> Descriptor {
+        Descriptor {
+            hash: Some(hash_value::<T>),
+            ..Descriptor::new_eq::<T>()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to order values of `T`.
+    ///
+    /// Since `Ord` implies `Eq`, the descriptor also records how to compare
+    /// values of `T`, as in `Descriptor::new_eq`.
+    ///
+    /// See `Descriptor::cmp_fn`.
+    #[inline]
+    pub const fn new_ord<T: Any + Ord>() -> Descriptor {
+        Descriptor {
+            cmp: Some(cmp_values::<T>),
+            ..Descriptor::new_eq::<T>()
+        }
+    }
+
+    /// Create a new Descriptor describing the type `T`, which also records
+    /// how to create default values of `T`.
+    ///
+    /// See `Descriptor::default_fn` and `Descriptor::make_default`.
+    #[inline]
+    pub const fn new_default<T: Any + Default>() -> Descriptor {
+        Descriptor {
+            default: Some(default_value::<T>),
+            ..Descriptor::new::<T>()
+        }
+    }
+
+}
+
+impl<M> Descriptor<M> {
+    /// Create a new Descriptor describing the type `T`, carrying the given
+    /// metadata.
+    #[inline]
+    pub const fn with_meta<T: Any>(meta: M) -> Descriptor<M> {
+        Descriptor::new::<T>().attach_meta(meta)
+    }
+
+    /// Read the metadata carried by this descriptor.
+    #[inline]
+    pub fn meta(&self) -> &M { &self.meta }
+
+    /// Copy this descriptor, replacing its metadata with `meta`.
+    #[inline]
+    pub const fn attach_meta<N>(&self, meta: N) -> Descriptor<N> {
+        Descriptor {
+            size: self.size,
+            alignment: self.alignment,
+            id: self.id,
+            name: self.name,
+            type_hash: self.type_hash,
+            needs_drop: self.needs_drop,
+            drop: self.drop,
+            clone: self.clone,
+            debug: self.debug,
+            display: self.display,
+            eq: self.eq,
+            hash: self.hash,
+            cmp: self.cmp,
+            default: self.default,
+            rebox: self.rebox,
+            unbox: self.unbox,
+            any: self.any,
+            error: self.error,
+            meta
+        }
+    }
+
+    /// Copy this descriptor without its metadata, as used by `Dynamic`.
+    #[inline]
+    pub const fn without_meta(&self) -> Descriptor { self.attach_meta(()) }
+
+    /// Read the human-readable name of the described type.
+    ///
+    /// The name is intended for diagnostics only: its exact contents are not
+    /// guaranteed to be stable across compiler versions, and distinct types
+    /// may share a name. Use `id` to identify types.
+    #[inline]
+    pub fn name(&self) -> &'static str { (self.name)() }
+
+    /// Get the `TypeId` of the described type.
+    #[inline]
+    pub fn id(&self) -> TypeId { self.id }
+
+    /// Get the size of the described type, in bytes.
+    #[inline]
+    pub fn size(&self) -> usize { self.size }
+
+    /// Get the alignment of the described type, in bytes.
+    #[inline]
+    pub fn alignment(&self) -> usize { self.alignment }
+
+    /// Get a hash of the `TypeId` of the described type.
+    ///
+    /// The hash is computed from the `TypeId`, through its `Hash` impl, with
+    /// a fixed hasher rather than a randomly seeded one. It is produced by a
+    /// function recorded for the described type, which the compiler folds
+    /// to a constant, so reading it does not hash the `TypeId` again. The
+    /// `Hash` impls of `Descriptor` and `Dynamic` feed it to the hasher in
+    /// place of the `TypeId`.
+    ///
+    /// Like a `TypeId`, the hash is only meaningful within a single build
+    /// of a program.
+    #[inline]
+    pub fn type_hash(&self) -> u64 { (self.type_hash)() }
+
+    /// Get the type token of the described type, which is the same as its
+    /// precomputed `type_hash`.
+    ///
+    /// See `type_token`.
+    #[inline(always)]
+    pub fn type_token(&self) -> u64 { (self.type_hash)() }
+
+    /// Check if dropping a value of the described type runs any code.
+    ///
+    /// If this returns `false`, the value can be discarded without running
+    /// its destructor.
+    #[inline]
+    pub fn needs_drop(&self) -> bool { self.needs_drop }
+
+    /// Get a function which drops a value of the described type in place.
+    ///
+    /// The function must only be called with a pointer to a valid value of
+    /// the described type, which must not be used again afterwards.
+    #[inline]
+    pub fn drop_fn(&self) -> unsafe fn(*mut ()) { self.drop }
+
+    /// Get a function which clones a value of the described type, if this
+    /// descriptor was created by `Descriptor::new_cloneable`.
+    ///
+    /// The function must only be called with a pointer to a valid value of
+    /// the described type. It returns a pointer to a clone of that value,
+    /// allocated as if by `Box::new`, which the caller is responsible for
+    /// freeing.
+    #[inline]
+    pub fn clone_fn(&self) -> Option<unsafe fn(*const ()) -> *mut ()> { self.clone }
+
+    /// Get a function which formats a value of the described type using
+    /// `Debug`, if this descriptor was created by `Descriptor::new_debug`.
+    ///
+    /// The function must only be called with a pointer to a valid value of
+    /// the described type.
+    #[inline]
+    pub fn debug_fn(&self) -> Option<unsafe fn(*const (), &mut fmt::Formatter) -> fmt::Result> {
+        self.debug
+    }
+
+    /// Get a function which formats a value of the described type using
+    /// `Display`, if this descriptor was created by `Descriptor::new_display`.
+    ///
+    /// The function must only be called with a pointer to a valid value of
+    /// the described type.
+    #[inline]
+    pub fn display_fn(&self) -> Option<unsafe fn(*const (), &mut fmt::Formatter) -> fmt::Result> {
+        self.display
+    }
+
+    /// Get a function which compares two values of the described type for
+    /// equality, if this descriptor was created by `Descriptor::new_eq`.
+    ///
+    /// The function must only be called with pointers to valid values of
+    /// the described type.
+    #[inline]
+    pub fn eq_fn(&self) -> Option<unsafe fn(*const (), *const ()) -> bool> { self.eq }
+
+    /// Get a function which feeds a value of the described type into a
+    /// `Hasher`, if this descriptor was created by `Descriptor::new_hash`.
+    ///
+    /// The function must only be called with a pointer to a valid value of
+    /// the described type.
+    #[inline]
+    pub fn hash_fn(&self) -> Option<unsafe fn(*const (), &mut dyn Hasher)> { self.hash }
+
+    /// Get a function which orders two values of the described type, if
+    /// this descriptor was created by `Descriptor::new_ord`.
+    ///
+    /// The function must only be called with pointers to valid values of
+    /// the described type.
+    #[inline]
+    pub fn cmp_fn(&self) -> Option<unsafe fn(*const (), *const ()) -> Ordering> { self.cmp }
+
+    /// Get a function which creates a default value of the described type,
+    /// if this descriptor was created by `Descriptor::new_default`.
+    ///
+    /// The function returns a pointer to the new value, allocated as if by
+    /// `Box::new`, which the caller is responsible for freeing.
+    #[inline]
+    pub fn default_fn(&self) -> Option<fn() -> *mut ()> { self.default }
+
+    /// Create a new Dynamic value containing the default value of the
+    /// described type, if this descriptor was created by
+    /// `Descriptor::new_default`.
+    ///
+    /// The resulting `Dynamic` is described by this descriptor. This is
+    /// safe since a descriptor can only be created by the constructors in
+    /// this crate, which always record the default function of the type
+    /// they describe.
+    #[inline]
+    pub fn make_default(&self) -> Option<Box<Dynamic>> {
+        // The default function creates a boxed value of the described type,
+        // and the private fields of the descriptor cannot be changed to
+        // describe another type.
+        self.default.map(|default| unsafe {
+            Dynamic::from_raw_parts(self.without_meta(), default())
+        })
+    }
+
+    /// Check if the described type is zero-sized.
+    #[inline]
+    pub fn is_zero_sized(&self) -> bool { self.size == 0 }
+
+    /// Get the memory layout of the described type.
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        // The size and alignment were read from a real type, so they always
+        // form a valid layout.
+        Layout::from_size_align(self.size, self.alignment).unwrap()
+    }
+
+    /// Check if storage for the described type can also hold values of the
+    /// type described by `other`, and vice versa.
+    ///
+    /// Unlike `==`, this ignores the identity of the types and only compares
+    /// their sizes and alignments.
+    #[inline]
+    pub fn layout_compatible<N>(&self, other: &Descriptor<N>) -> bool {
+        self.size == other.size && self.alignment == other.alignment
+    }
+
+    /// Get the portable parts of this descriptor.
+    #[inline]
+    pub fn info(&self) -> DescriptorInfo { DescriptorInfo::from(self) }
+
+    /// Get the size and alignment of the described type in a form suitable
+    /// for passing across an FFI boundary.
+    #[inline]
+    pub fn raw_layout(&self) -> RawLayout {
+        RawLayout {
+            size: self.size,
+            alignment: self.alignment
+        }
+    }
+}
+
+/// The portable parts of a `Descriptor`, for persisting or reporting which
+/// types were in use.
+///
+/// A `DescriptorInfo` is informational only: it does not contain a `TypeId`,
+/// which has no stable representation, so it cannot be turned back into a
+/// live `Descriptor`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DescriptorInfo {
+    /// The name of the type, as given by `Descriptor::name`.
+    pub name: String,
+
+    /// The size of the type, in bytes.
+    pub size: usize,
+
+    /// The alignment of the type, in bytes.
+    pub alignment: usize
+}
+
+impl<'a, M> From<&'a Descriptor<M>> for DescriptorInfo {
+    #[inline]
+    fn from(desc: &'a Descriptor<M>) -> DescriptorInfo {
+        DescriptorInfo {
+            name: desc.name().to_owned(),
+            size: desc.size(),
+            alignment: desc.alignment()
+        }
+    }
+}
+
+/// The size and alignment of a type, with a stable `#[repr(C)]` layout.
+///
+/// Equivalent to the C struct `struct { size_t size; size_t alignment; }`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct RawLayout {
+    /// The size of the type, in bytes.
+    pub size: usize,
+
+    /// The alignment of the type, in bytes.
+    pub alignment: usize
+}
+
+/// Get the type token of the type `T`, for use with `Dynamic::is_token`.
+///
+/// A token is the hash of the `TypeId` of `T`, as returned by
+/// `Descriptor::type_hash`. Like a `TypeId`, it is only meaningful within a
+/// single build of a program. Unlike a `TypeId`, distinct types may in
+/// theory share a token, so tokens are only an optimization over
+/// `Dynamic::is`:
+///
+/// ```
+/// use dynamic::{Dynamic, type_token};
+///
+/// let values = vec![Dynamic::new(1u8), Dynamic::new(2i32), Dynamic::new(3u8)];
+///
+/// let token = type_token::<u8>();
+/// let bytes = values.iter()
+///     .filter(|val| val.is_token(token))
+///     .filter_map(|val| val.downcast_ref::<u8>())
+///     .sum::<u8>();
+/// assert_eq!(bytes, 4);
+/// ```
+#[inline(always)]
+pub fn type_token<T: Any>() -> u64 { type_hash::<T>() }
+
+/// Get the Descriptor of the type `T`.
+///
+/// Equivalent to `Descriptor::new::<T>()`.
+#[inline]
+pub const fn descriptor_of<T: Any>() -> Descriptor {
+    Descriptor::new::<T>()
+}
+
+impl<M: fmt::Debug> fmt::Debug for Descriptor<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Descriptor")
+            .field("name", &self.name())
+            .field("id", &self.id)
+            .field("size", &self.size)
+            .field("alignment", &self.alignment)
+            .field("meta", &self.meta)
+            .finish()
+    }
+}
+
+impl<M> PartialEq for Descriptor<M> {
+    #[inline]
+    fn eq(&self, other: &Descriptor<M>) -> bool { self.id == other.id }
+}
+
+impl<M> Eq for Descriptor<M> {}
+
+impl<M> PartialOrd for Descriptor<M> {
+    #[inline]
+    fn partial_cmp(&self, other: &Descriptor<M>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<M> Ord for Descriptor<M> {
+    #[inline]
+    fn cmp(&self, other: &Descriptor<M>) -> Ordering { self.id.cmp(&other.id) }
+}
+
+impl<M> Hash for Descriptor<M> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) { state.write_u64(self.type_hash()) }
+}
+
+// Catch described values whose descriptor does not match their layout, for
+// instance after a misused `Described::from_parts`, before they are erased.
+#[inline(always)]
+fn debug_check_described<T>(val: &Described<T>) {
+    debug_assert_eq!(val.desc.alignment, mem::align_of::<T>(),
+                     "the descriptor does not match the alignment of `{}`", any::type_name::<T>());
+    debug_assert!((&val.data as *const T).is_aligned(), "the described value is misaligned");
+}
+
+// A 64-bit FNV-1a hasher, used to hash TypeIds independently of the
+// randomly seeded std hashers.
+struct FixedHasher(u64);
+
+impl Default for FixedHasher {
+    #[inline]
+    fn default() -> FixedHasher { FixedHasher(0xcbf2_9ce4_8422_2325) }
+}
+
+impl Hasher for FixedHasher {
+    #[inline]
+    fn finish(&self) -> u64 { self.0 }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[inline]
+fn hash_id(id: &TypeId) -> u64 {
+    let mut hasher = FixedHasher::default();
+    id.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn type_hash<T: Any>() -> u64 { hash_id(&TypeId::of::<T>()) }
+
+unsafe fn drop_value<T>(ptr: *mut ()) {
+    ptr::drop_in_place(ptr as *mut T)
+}
+
+unsafe fn clone_value<T: Clone>(ptr: *const ()) -> *mut () {
+    Box::into_raw(Box::new((*(ptr as *const T)).clone())) as *mut ()
+}
+
+unsafe fn debug_value<T: fmt::Debug>(ptr: *const (), f: &mut fmt::Formatter) -> fmt::Result {
+    fmt::Debug::fmt(&*(ptr as *const T), f)
+}
+
+unsafe fn display_value<T: fmt::Display>(ptr: *const (), f: &mut fmt::Formatter) -> fmt::Result {
+    fmt::Display::fmt(&*(ptr as *const T), f)
+}
+
+unsafe fn eq_values<T: PartialEq>(a: *const (), b: *const ()) -> bool {
+    *(a as *const T) == *(b as *const T)
+}
+
+unsafe fn hash_value<T: Hash>(ptr: *const (), mut state: &mut dyn Hasher) {
+    (*(ptr as *const T)).hash(&mut state)
+}
+
+unsafe fn cmp_values<T: Ord>(a: *const (), b: *const ()) -> Ordering {
+    (*(a as *const T)).cmp(&*(b as *const T))
+}
+
+fn default_value<T: Default>() -> *mut () {
+    Box::into_raw(Box::<T>::default()) as *mut ()
+}
+
+unsafe fn rebox_value<T: Any>(desc: Descriptor, ptr: *mut ()) -> Box<Dynamic> {
+    Dynamic::from_described(Described {
+        desc,
+        data: *Box::from_raw(ptr as *mut T)
+    })
+}
+
+unsafe fn unbox_value<T>(ptr: *mut ()) -> *mut () {
+    Box::into_raw(Box::new(Box::from_raw(ptr as *mut Described<T>).data)) as *mut ()
+}
+
+fn any_value<T: Any>(ptr: *mut ()) -> *mut dyn Any {
+    ptr as *mut T as *mut dyn Any
+}
+
+fn error_value<T: Error + 'static>(ptr: *mut ()) -> *mut dyn Error {
+    ptr as *mut T as *mut dyn Error
+}
+
+// Empty trait for small vtables.
+trait Dyn {}
+impl<T> Dyn for T {}
+
+#[cfg(test)]
+mod test {
+    use std::alloc::Layout;
+    use std::any::TypeId;
+    use std::boxed::Box;
+    use std::cell::Cell;
+    use std::collections::{HashMap, HashSet};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::mem::{self, ManuallyDrop};
+    use std::marker::PhantomPinned;
+    use std::num::ParseIntError;
+    use std::ptr;
+    use std::rc::Rc;
+    use std::string::{String, ToString};
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+    use std::{format, vec};
+    use {Dynamic, Described, Descriptor, DescriptorInfo, FixedHasher, DowncastError, RawLayout, TryMapError, descriptor_of, hash_id,
+         type_token};
+
+    #[derive(Clone, Debug)]
+    struct X(usize);
+    struct Y;
+    struct Z(usize);
+
+    struct Dropper(Rc<Cell<usize>>);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    #[test]
+    fn test_downcasting() {
+        let mut x = Dynamic::new(X(1));
+
+        assert!(x.is::<X>());
+        assert!(!x.is::<Y>());
+        assert!(!x.is::<Z>());
+        assert_eq!(x.type_id(), TypeId::of::<X>());
+        assert_eq!(x.type_id(), x.id());
+        assert!(x.is_id(TypeId::of::<X>()));
+        assert!(!x.is_id(TypeId::of::<Y>()));
+
+        *x.downcast_mut::<X>().unwrap() = X(100);
+        assert_eq!(x.downcast_ref::<X>().unwrap().0, 100);
+
+        assert_eq!(unsafe { x.downcast_ref_unchecked::<X>() }.0, 100);
+
+        let described_x = x.downcast::<X>().unwrap();
+        assert_eq!(described_x.id(), TypeId::of::<X>());
+        assert_eq!(described_x.data.0, 100);
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut x = Dynamic::new(X(1));
+
+        assert_eq!(x.replace(X(42)).ok().unwrap().0, 1);
+        assert_eq!(x.downcast_ref::<X>().unwrap().0, 42);
+
+        assert_eq!(x.replace(Z(7)).err().unwrap().0, 7);
+        assert_eq!(x.downcast_ref::<X>().unwrap().0, 42);
+    }
+
+    #[test]
+    fn test_swap() {
+        let (mut a, mut b) = (Dynamic::new(X(1)), Dynamic::new(X(2)));
+        assert!(a.swap(&mut b));
+        assert_eq!(a.downcast_ref::<X>().unwrap().0, 2);
+        assert_eq!(b.downcast_ref::<X>().unwrap().0, 1);
+
+        let mut s = Dynamic::new(String::from("s"));
+        assert!(!a.swap(&mut s));
+        assert_eq!(a.downcast_ref::<X>().unwrap().0, 2);
+        assert_eq!(s.downcast_ref::<String>().unwrap(), "s");
+
+        // Every byte of the value is swapped, not just a prefix.
+        let mut t = Dynamic::new(String::from("a longer string"));
+        assert!(s.swap(&mut t));
+        assert_eq!(s.downcast_ref::<String>().unwrap(), "a longer string");
+        assert_eq!(t.downcast_ref::<String>().unwrap(), "s");
+    }
+
+    #[test]
+    fn test_downcast_mut_unchecked() {
+        let mut column = vec![Dynamic::new(X(1)), Dynamic::new(X(2))];
+
+        // Every element of the column is known to be an X.
+        for x in &mut column {
+            unsafe { x.downcast_mut_unchecked::<X>().0 *= 10 };
+        }
+
+        assert_eq!(column[0].downcast_ref::<X>().unwrap().0, 10);
+        assert_eq!(column[1].downcast_ref::<X>().unwrap().0, 20);
+    }
+
+    #[test]
+    fn test_downcast_ptr_by_id() {
+        let x = Dynamic::new(X(5));
+
+        let ptr = x.downcast_ptr_by_id(TypeId::of::<X>()).unwrap();
+        assert_eq!(unsafe { &*(ptr as *const X) }.0, 5);
+        assert_eq!(ptr, x.downcast_ref::<X>().unwrap() as *const X as *const ());
+
+        assert!(x.downcast_ptr_by_id(TypeId::of::<Y>()).is_none());
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let s = Dynamic::new(String::from("owned"));
+        let s = s.into_inner::<X>().unwrap_err();
+        assert_eq!(s.into_inner::<String>().unwrap(), "owned");
+    }
+
+    #[test]
+    fn test_map() {
+        let x = Dynamic::new(5i32);
+
+        let x = x.map(|x: String| x.len()).unwrap_err();
+        let s = x.map(|x: i32| (x * 2).to_string()).unwrap();
+        assert_eq!(s.downcast_ref::<String>().unwrap(), "10");
+    }
+
+    #[test]
+    fn test_try_map() {
+        fn parse(s: String) -> Result<i32, ParseIntError> { s.parse() }
+
+        let input = Dynamic::new(String::from("12"));
+        let parsed = input.try_map(parse).unwrap();
+        assert_eq!(parsed.downcast_ref::<i32>(), Some(&12));
+
+        match parsed.try_map(parse) {
+            Err(TryMapError::WrongType(original)) =>
+                assert_eq!(original.downcast_ref::<i32>(), Some(&12)),
+            _ => panic!("expected the wrong type")
+        }
+
+        match Dynamic::new(String::from("twelve")).try_map(parse) {
+            Err(TryMapError::Failed(err)) => assert_eq!(err, "x".parse::<i32>().unwrap_err()),
+            _ => panic!("expected a failure")
+        }
+    }
+
+    #[test]
+    fn test_try_downcast() {
+        let x = Dynamic::new(X(1));
+        let x = x.try_downcast::<X>().unwrap();
+        assert_eq!(x.data.0, 1);
+
+        let err = Box::<Dynamic>::from(x).try_downcast::<String>().unwrap_err();
+        assert_eq!(err.expected(), TypeId::of::<String>());
+        assert!(err.expected_name().ends_with("String"));
+        assert_eq!(err.actual(), Descriptor::new::<X>());
+        assert!(err.actual().name().ends_with("X"));
+        assert_eq!(err.into_inner().downcast_ref::<X>().unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_downcast_error() {
+        use std::error::Error;
+
+        fn load(val: Box<Dynamic>) -> Result<u8, Box<dyn Error>> {
+            Ok(val.try_downcast::<u8>()?.data)
+        }
+
+        assert_eq!(load(Dynamic::new(1u8)).unwrap(), 1);
+
+        let err = load(Dynamic::new(1i32)).unwrap_err();
+        assert_eq!(err.to_string(), "expected a `u8`, found a `i32`");
+
+        let err = *err.downcast::<DowncastError>().unwrap();
+        assert!(format!("{:?}", err).contains("DowncastError"));
+        assert_eq!(Box::<Dynamic>::from(err).downcast_ref::<i32>(), Some(&1));
+    }
+
+    #[test]
+    fn test_as_any() {
+        let x = Dynamic::new(X(3));
+        let any = x.as_any();
+
+        assert_eq!(any.downcast_ref::<X>().unwrap().0, 3);
+        assert!(any.downcast_ref::<Y>().is_none());
+        assert!(ptr::eq(any.downcast_ref::<X>().unwrap(), x.downcast_ref::<X>().unwrap()));
+    }
+
+    #[test]
+    fn test_deref_any() {
+        use std::any::Any;
+
+        fn unwrap_x(any: &dyn Any) -> usize { any.downcast_ref::<X>().unwrap().0 }
+
+        let x = Dynamic::new(X(4));
+        assert_eq!(Any::type_id(&**x), x.descriptor().id());
+        assert_eq!(unwrap_x(&**x), 4);
+    }
+
+    #[test]
+    fn test_borrow_any() {
+        use std::any::Any;
+        use std::borrow::{Borrow, BorrowMut};
+
+        fn find<B: Borrow<dyn Any>>(items: &[B]) -> Option<&X> {
+            items.iter().filter_map(|item| item.borrow().downcast_ref::<X>()).next()
+        }
+
+        let mut items = vec![Dynamic::new(1u8), Dynamic::new(X(2)), Dynamic::new(Y)];
+        assert_eq!(find(&items).unwrap().0, 2);
+        assert!(ptr::eq(find(&items).unwrap(), items[1].downcast_ref::<X>().unwrap()));
+
+        BorrowMut::<dyn Any>::borrow_mut(&mut items[1]).downcast_mut::<X>().unwrap().0 = 3;
+        assert_eq!(find(&items).unwrap().0, 3);
+        assert_eq!(items[0].as_any_mut().downcast_mut::<u8>(), Some(&mut 1));
+    }
+
+    #[test]
+    fn test_as_ref_any() {
+        use std::any::Any;
+
+        fn unwrap_x<A: AsRef<dyn Any> + ?Sized>(any: &A) -> &X {
+            any.as_ref().downcast_ref::<X>().unwrap()
+        }
+
+        let x = Dynamic::new(X(6));
+        assert_eq!(unwrap_x(&*x).0, 6);
+        assert!(ptr::eq(unwrap_x(&*x), x.downcast_ref::<X>().unwrap()));
+    }
+
+    #[test]
+    fn test_into_any() {
+        let any = Dynamic::new(X(5)).into_any();
+        assert_eq!(any.downcast::<X>().unwrap().0, 5);
+
+        let drops = Rc::new(Cell::new(0));
+        let any = Dynamic::new(Dropper(drops.clone())).into_any();
+        assert_eq!(drops.get(), 0);
+        drop(any);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn test_from_any() {
+        use std::any::Any;
+
+        let wrapped = Box::<Dynamic>::from(Box::new(7i32) as Box<dyn Any>);
+        assert!(wrapped.is::<Box<dyn Any>>());
+        let inner = wrapped.into_inner::<Box<dyn Any>>().unwrap();
+
+        let unwrapped = Dynamic::from_any::<i32>(inner).unwrap();
+        assert_eq!(unwrapped.descriptor(), Descriptor::new::<i32>());
+        assert_eq!(unwrapped.downcast_ref::<i32>(), Some(&7));
+
+        let wrong = Dynamic::from_any::<u8>(Box::new(7i32)).unwrap_err();
+        assert_eq!(wrong.downcast_ref::<i32>(), Some(&7));
+    }
+
+    #[test]
+    fn test_from_box() {
+        use std::convert::TryInto;
+
+        let x = Dynamic::from_box(Box::new(X(9)));
+        assert_eq!(x.descriptor(), Descriptor::new::<X>());
+        assert_eq!(x.downcast_ref::<X>().unwrap().0, 9);
+
+        let drops = Rc::new(Cell::new(0));
+        let dropper = Dynamic::from_box(Box::new(Dropper(drops.clone())));
+        assert_eq!(drops.get(), 0);
+        drop(dropper);
+        assert_eq!(drops.get(), 1);
+
+        // Larger than a test thread's stack, so this only passes if the
+        // payload is never copied onto the stack.
+        const LARGE: usize = 4 << 20;
+        let large: Box<[u8; LARGE]> = vec![7; LARGE].into_boxed_slice().try_into().unwrap();
+        let large = Dynamic::from_box(large);
+        assert!(large.downcast_ref::<[u8; LARGE]>().unwrap().iter().all(|&b| b == 7));
+    }
+
+    #[test]
+    fn test_downcast_into_box() {
+        let s = Dynamic::new(String::from("boxed"));
+        let s = s.downcast_into_box::<X>().unwrap_err();
+        assert_eq!(*s.downcast_into_box::<String>().unwrap(), "boxed");
+
+        let drops = Rc::new(Cell::new(0));
+        let dropper = Dynamic::new(Dropper(drops.clone())).downcast_into_box::<Dropper>();
+        assert_eq!(drops.get(), 0);
+        drop(dropper);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn test_as_ptr() {
+        let mut x = Dynamic::new(X(4));
+
+        let ptr = x.as_ptr() as *const X;
+        assert!(ptr::eq(unsafe { &*ptr }, x.downcast_ref::<X>().unwrap()));
+
+        let ptr = x.as_mut_ptr() as *mut X;
+        unsafe { (*ptr).0 = 8 };
+        assert_eq!(x.downcast_ref::<X>().unwrap().0, 8);
+    }
+
+    #[test]
+    fn test_as_bytes() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(C)]
+        struct Pixel { r: u8, g: u8, b: u8, a: u8, depth: u32 }
+
+        unsafe fn from_bytes<T: Copy>(bytes: &[u8]) -> T {
+            assert_eq!(bytes.len(), mem::size_of::<T>());
+            ptr::read_unaligned(bytes.as_ptr() as *const T)
+        }
+
+        let pixel = Pixel { r: 1, g: 2, b: 3, a: 4, depth: 0x01020304 };
+        let dynamic = Dynamic::new(pixel);
+        let bytes = unsafe { dynamic.as_bytes() };
+
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(&bytes[..4], &[1, 2, 3, 4]);
+        assert_eq!(unsafe { from_bytes::<Pixel>(bytes) }, pixel);
+        assert_eq!(unsafe { Dynamic::new(()).as_bytes() }, &[] as &[u8]);
+    }
+
+    #[test]
+    fn test_raw_parts() {
+        let (desc, data) = Dynamic::new_cloneable(String::from("parts")).into_raw_parts();
+        assert_eq!(desc, Descriptor::new::<String>());
+        assert_eq!(unsafe { &*(data as *const String) }, "parts");
+
+        let dynamic = unsafe { Dynamic::from_raw_parts(desc, data) };
+        assert!(dynamic.descriptor().clone_fn().is_some());
+        assert_eq!(dynamic.downcast_ref::<String>().unwrap(), "parts");
+
+        let data = Box::into_raw(Box::new(X(3))) as *mut ();
+        let dynamic = unsafe { Dynamic::from_raw_parts(Descriptor::new::<X>(), data) };
+        assert_eq!(dynamic.downcast_ref::<X>().unwrap().0, 3);
+    }
+
+    #[test]
+    fn test_downcast_ref_or() {
+        let fallback = X(0);
+        let (x, y) = (Dynamic::new(X(1)), Dynamic::new(Y));
+
+        assert_eq!(x.downcast_ref_or(&fallback).0, 1);
+        assert_eq!(y.downcast_ref_or(&fallback).0, 0);
+
+        assert_eq!(x.downcast_ref_or_else::<X, _>(|| panic!("not lazy")).0, 1);
+        assert_eq!(y.downcast_ref_or_else(|| &fallback).0, 0);
+    }
+
+    #[test]
+    fn test_dynamic_refs() {
+        let described_z = Described::new(Z(1000));
+
+        let z_ref = Dynamic::from_ref(&described_z);
+        assert_eq!(z_ref.downcast_ref::<Z>().unwrap().0, 1000);
+    }
+
+    #[test]
+    fn test_dynamic_refs_alignment() {
+        #[derive(Debug, PartialEq)]
+        #[repr(align(64))]
+        struct Aligned(u8);
+
+        let mut aligned = Described::new(Aligned(1));
+        assert_eq!(Dynamic::from_ref(&aligned).downcast_ref::<Aligned>(), Some(&Aligned(1)));
+        Dynamic::from_mut(&mut aligned).downcast_mut::<Aligned>().unwrap().0 = 2;
+        assert_eq!(aligned.data, Aligned(2));
+
+        let mut unit = Described::new(());
+        assert_eq!(Dynamic::from_ref(&unit).downcast_ref::<()>(), Some(&()));
+        assert!(Dynamic::from_mut(&mut unit).downcast_mut::<()>().is_some());
+
+        let bytes = Described::new([1u8; 3]);
+        assert_eq!(Dynamic::from_ref(&bytes).downcast_ref::<[u8; 3]>(), Some(&[1; 3]));
+    }
+
+    #[test]
+    fn test_downcast_layouts() {
+        use std::any::Any;
+        use std::fmt::Debug;
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Unit;
+
+        #[derive(Clone, Debug, PartialEq)]
+        #[repr(align(64))]
+        struct Align64(u8);
+
+        #[derive(Clone, Debug, PartialEq)]
+        #[repr(align(4096))]
+        struct Align4096([u16; 3]);
+
+        #[derive(Clone, Debug, PartialEq)]
+        #[repr(align(32))]
+        struct AlignedUnit;
+
+        fn check<T: Any + Clone + Debug + PartialEq>(val: T) {
+            let dynamic = Dynamic::new(val.clone());
+            assert_eq!(mem::size_of_val(&*dynamic), mem::size_of::<Described<T>>());
+            assert_eq!(mem::align_of_val(&*dynamic), mem::align_of::<Described<T>>());
+            assert!((dynamic.as_ptr() as *const T).is_aligned());
+            assert_eq!(dynamic.downcast_ref::<T>(), Some(&val));
+
+            let addr = dynamic.as_ptr();
+            let described = dynamic.downcast::<T>().unwrap();
+            assert_eq!(&described.data as *const T as *const (), addr);
+            assert_eq!(described.data, val);
+
+            let mut described = Described::new(val.clone());
+            assert_eq!(Dynamic::from_mut(&mut described).downcast_mut::<T>(), Some(&mut val.clone()));
+
+            let rc = Dynamic::new_rc(val.clone()).downcast_rc::<T>().unwrap();
+            assert_eq!(rc.data, val);
+            let arc = Arc::<Dynamic>::from(Dynamic::new(val.clone())).downcast_arc::<T>().unwrap();
+            assert_eq!(arc.data, val);
+
+            let (desc, data) = Dynamic::new(val.clone()).into_raw_parts();
+            let dynamic = unsafe { Dynamic::from_raw_parts(desc, data) };
+            assert_eq!(dynamic.into_inner::<T>().ok(), Some(val));
+        }
+
+        check(1u8);
+        check(2u16);
+        check(3u64);
+        check(4u128);
+        check([5u8; 3]);
+        check(());
+        check(Unit);
+        check(AlignedUnit);
+        check(Align64(6));
+        check(Align4096([7; 3]));
+        check(vec![Align64(8), Align64(9)]);
+        check([10u64; 512]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "the descriptor does not match the alignment")]
+    fn test_dynamic_refs_wrong_descriptor() {
+        let described = Described { desc: Descriptor::new::<u8>(), data: 1u64 };
+        Dynamic::from_ref(&described);
+    }
+
+    #[test]
+    fn test_described_map() {
+        let described = Described::new(5i32).map(|x| x.to_string());
+        assert_eq!(described.descriptor(), Descriptor::new::<String>());
+        assert_eq!(described.descriptor().name(), Descriptor::new::<String>().name());
+        assert_eq!(described.data, "5");
+    }
+
+    #[test]
+    fn test_described_deref() {
+        let described = Described::new(String::from("four"));
+        assert_eq!(described.len(), 4);
+        assert_eq!(&*described, "four");
+    }
+
+    #[test]
+    fn test_described_deref_mut() {
+        let mut described = Described::new(Vec::new());
+        described.push(1);
+        described.extend([2, 3]);
+
+        assert_eq!(*described, [1, 2, 3]);
+        assert_eq!(described.descriptor(), Descriptor::new::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_described_from() {
+        let described: Described<X> = X(3).into();
+        assert_eq!(described.descriptor(), Descriptor::new::<X>());
+        assert_eq!(described.data.0, 3);
+
+        let all: Vec<Described<u8>> = (1..4).map(Described::from).collect();
+        assert_eq!(all.iter().map(|d| d.data).sum::<u8>(), 6);
+    }
+
+    #[test]
+    fn test_described_into_inner() {
+        let described = Described::new(String::from("inner"));
+        assert_eq!(described.into_inner(), "inner");
+
+        let downcasted = Dynamic::new(X(8)).downcast::<X>().unwrap();
+        assert_eq!(downcasted.into_inner().0, 8);
+    }
+
+    #[test]
+    fn test_described_as_dynamic() {
+        let described = Described::new(X(11));
+        let dynamic = described.as_dynamic();
+
+        assert_eq!(dynamic.downcast_ref::<X>().unwrap().0, 11);
+        assert!(ptr::eq(dynamic.downcast_ref::<X>().unwrap(), &described.data));
+    }
+
+    #[test]
+    fn test_described_as_dynamic_mut() {
+        let mut described = Described::new(X(11));
+        described.as_dynamic_mut().downcast_mut::<X>().unwrap().0 = 12;
+        assert!(described.as_dynamic_mut().downcast_mut::<Y>().is_none());
+
+        assert_eq!(described.data.0, 12);
+    }
+
+    #[test]
+    fn test_described_into_dynamic() {
+        let dynamic = Described::new(X(13)).into_dynamic();
+        assert_eq!(dynamic.descriptor(), Descriptor::new::<X>());
+        assert_eq!(dynamic.downcast::<X>().unwrap().data.0, 13);
+
+        let described = Dynamic::new_cloneable(X(14)).downcast::<X>().unwrap();
+        let dynamic = described.into_dynamic();
+        assert_eq!(dynamic.clone().downcast_ref::<X>().unwrap().0, 14);
+    }
+
+    #[test]
+    fn test_from_described_box() {
+        let boxed = Box::new(Described::new(X(7)));
+        let data = &boxed.data as *const X;
+
+        let dynamic = Box::<Dynamic>::from(boxed);
+        assert!(ptr::eq(dynamic.downcast_ref::<X>().unwrap(), data));
+        assert_eq!(dynamic.downcast::<X>().unwrap().data.0, 7);
+    }
+
+    #[test]
+    fn test_try_from_dynamic() {
+        use std::convert::TryFrom;
+
+        let dynamic = Dynamic::new(X(15));
+        let dynamic = Box::<Described<String>>::try_from(dynamic).unwrap_err().into_inner();
+        assert_eq!(dynamic.downcast_ref::<X>().unwrap().0, 15);
+
+        let described = Box::<Described<X>>::try_from(dynamic).unwrap();
+        assert_eq!(described.data.0, 15);
+    }
+
+    #[test]
+    fn test_described_from_parts() {
+        let described = unsafe { Described::from_parts(Descriptor::new_cloneable::<X>(), X(16)) };
+        assert_eq!(described.descriptor(), Descriptor::new::<X>());
+
+        let dynamic = described.into_dynamic();
+        assert_eq!(dynamic.clone().downcast_ref::<X>().unwrap().0, 16);
+        assert_eq!(dynamic.downcast::<X>().unwrap().data.0, 16);
+    }
+
+    #[test]
+    fn test_described_as_ref() {
+        fn len<S: AsRef<String>>(s: S) -> usize { s.as_ref().len() }
+
+        let described = Described::new(String::from("as ref"));
+        assert_eq!(len(&described), 6);
+        assert_eq!(len(described), 6);
+    }
+
+    #[test]
+    fn test_described_borrow() {
+        let mut set = HashSet::new();
+        set.insert(Described::new(String::from("key")));
+
+        let key = String::from("key");
+        assert!(set.contains(&key));
+        assert!(!set.contains(&String::from("other")));
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let mut described_hasher = DefaultHasher::new();
+        Described::new(key).hash(&mut described_hasher);
+        assert_eq!(hasher.finish(), described_hasher.finish());
+    }
+
+    #[test]
+    fn test_described_default() {
+        let described = Described::<i32>::default();
+        assert_eq!(described.data, 0);
+        assert_eq!(described.descriptor(), Descriptor::new::<i32>());
+    }
+
+    #[test]
+    fn test_descriptor_name() {
+        let desc = Descriptor::new::<X>();
+        assert!(desc.name().ends_with("X"));
+        assert_eq!(desc.id(), TypeId::of::<X>());
+
+        let x = Dynamic::new(X(1));
+        assert_eq!(x.descriptor(), desc);
+        assert!(format!("{:?}", x).contains(desc.name()));
+    }
+
+    #[test]
+    fn test_descriptor_type_hash() {
+        use std::any::Any;
+
+        fn check<T: Any>() {
+            let desc = Descriptor::new::<T>();
+            assert_eq!(desc.type_hash(), hash_id(&TypeId::of::<T>()));
+            assert_eq!(desc.type_hash(), Descriptor::with_meta::<T>(1).type_hash());
+            assert_eq!(desc.type_hash(), Descriptor::builder::<T>().build().type_hash());
+        }
+
+        check::<X>();
+        check::<String>();
+        check::<()>();
+
+        // The hash is that of the TypeId under the fixed hasher.
+        let mut hasher = FixedHasher::default();
+        TypeId::of::<X>().hash(&mut hasher);
+        let desc = Descriptor::new::<X>();
+        assert_eq!(desc.type_hash(), hasher.finish());
+        assert_ne!(desc.type_hash(), Descriptor::new::<String>().type_hash());
+
+        let mut descriptors = HashSet::new();
+        descriptors.insert(Descriptor::new::<X>());
+        descriptors.insert(Descriptor::new_cloneable::<String>());
+        assert!(descriptors.contains(&desc));
+        assert!(descriptors.contains(&Descriptor::new::<String>()));
+        assert!(!descriptors.contains(&Descriptor::new::<u8>()));
+    }
+
+    #[test]
+    fn test_type_token() {
+        let values = vec![
+            Dynamic::new(X(1)),
+            Dynamic::new(1u8),
+            Dynamic::new(String::from("s")),
+            Dynamic::new(()),
+            Dynamic::new(Box::new(1u8))
+        ];
+
+        for val in &values {
+            assert_eq!(val.is_token(type_token::<X>()), val.is::<X>());
+            assert_eq!(val.is_token(type_token::<u8>()), val.is::<u8>());
+            assert_eq!(val.is_token(type_token::<String>()), val.is::<String>());
+            assert_eq!(val.is_token(type_token::<()>()), val.is::<()>());
+            assert_eq!(val.is_token(type_token::<Box<u8>>()), val.is::<Box<u8>>());
+            assert_eq!(val.descriptor().type_token(), val.descriptor().attach_meta(1).type_token());
+            assert_eq!(val.descriptor().type_token(), val.descriptor().type_hash());
+        }
+
+        assert_eq!(type_token::<X>(), hash_id(&TypeId::of::<X>()));
+    }
+
+    #[test]
+    fn test_descriptor_needs_drop() {
+        assert!(!Descriptor::new::<X>().needs_drop());
+        assert!(!Descriptor::new::<(u8, usize)>().needs_drop());
+        assert!(Descriptor::new::<String>().needs_drop());
+        assert!(Descriptor::new::<Vec<X>>().needs_drop());
+    }
+
+    #[test]
+    fn test_const_descriptor() {
+        const X_DESC: Descriptor = Descriptor::new::<X>();
+        static TABLE: [Descriptor; 2] = [Descriptor::new::<Y>(),
+                                         Descriptor::new::<Z>()];
+
+        assert_eq!(X_DESC, Descriptor::new::<X>());
+        assert_eq!(X_DESC.name(), Descriptor::new::<X>().name());
+        assert!(Dynamic::new(Z(1)).descriptor() == TABLE[1]);
+    }
+
+    #[test]
+    fn test_descriptor_of() {
+        assert_eq!(descriptor_of::<X>(), Descriptor::new::<X>());
+        assert_eq!(descriptor_of::<X>().name(), Descriptor::new::<X>().name());
+        assert!(descriptor_of::<X>() != descriptor_of::<Y>());
+
+        let descs: Vec<Descriptor> = (0..3).map(|_| descriptor_of::<Z>()).collect();
+        assert!(descs.iter().all(|desc| desc.id() == TypeId::of::<Z>()));
+    }
+
+    #[test]
+    fn test_drop() {
+        let drops = Rc::new(Cell::new(0));
+
+        drop(Dynamic::new(Dropper(drops.clone())));
+        assert_eq!(drops.get(), 1);
+
+        let downcasted = Dynamic::new(Dropper(drops.clone())).downcast::<Dropper>();
+        assert_eq!(drops.get(), 1);
+        drop(downcasted);
+        assert_eq!(drops.get(), 2);
+
+        let mismatched = Dynamic::new(Dropper(drops.clone())).downcast::<X>();
+        assert_eq!(drops.get(), 2);
+        drop(mismatched);
+        assert_eq!(drops.get(), 3);
+
+        let described = Described::new(Dropper(drops.clone()));
+        let _ = Dynamic::from_ref(&described);
+        assert_eq!(drops.get(), 3);
+        drop(described);
+        assert_eq!(drops.get(), 4);
+    }
+
+    #[test]
+    fn test_rc() {
+        let drops = Rc::new(Cell::new(0));
+        let x = Dynamic::new_rc(Dropper(drops.clone()));
+        let y = x.clone();
+        assert_eq!(Rc::strong_count(&x), 2);
+
+        let x = x.downcast_rc::<X>().unwrap_err();
+        assert_eq!(Rc::strong_count(&x), 2);
+
+        let x = x.downcast_rc::<Dropper>().unwrap();
+        assert_eq!(Rc::strong_count(&x), 2);
+        assert!(Rc::ptr_eq(&x.data.0, &drops));
+        assert!(y.is::<Dropper>());
+
+        drop(x);
+        assert_eq!(drops.get(), 0);
+        assert_eq!(Rc::strong_count(&y), 1);
+        drop(y);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn test_arc() {
+        let x = Arc::<Dynamic>::from(Dynamic::new(X(3)));
+        let y = x.clone();
+
+        let x = x.downcast_arc::<String>().unwrap_err();
+        assert_eq!(Arc::strong_count(&x), 2);
+
+        let x = x.downcast_arc::<X>().unwrap();
+        assert_eq!(x.data.0, 3);
+        assert_eq!(Arc::strong_count(&x), 2);
+        assert_eq!(y.downcast_ref::<X>().unwrap().0, 3);
+    }
+
+    #[test]
+    fn test_arc_threads() {
+        let x = Dynamic::new_arc(String::from("shared"));
+
+        let handles = (0..2).map(|_| {
+            let x = x.clone();
+            thread::spawn(move || x.downcast_arc::<String>().unwrap().data.len())
+        }).collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 6);
+        }
+
+        assert_eq!(Arc::strong_count(&x), 1);
+        assert_eq!(x.downcast_ref::<String>().unwrap(), "shared");
+    }
+
+    #[test]
+    fn test_weak() {
+        let strong = Arc::<Dynamic>::from(Dynamic::new(X(4)));
+        let weak = Arc::downgrade(&strong);
+
+        assert!(weak.upgrade().unwrap().is::<X>());
+        assert_eq!(Dynamic::upgrade_downcast::<X>(&weak).unwrap().data.0, 4);
+        assert!(Dynamic::upgrade_downcast::<Y>(&weak).is_none());
+        assert_eq!(Arc::strong_count(&strong), 1);
+        assert_eq!(Arc::weak_count(&strong), 1);
+
+        drop(strong);
+        assert!(weak.upgrade().is_none());
+        assert!(Dynamic::upgrade_downcast::<X>(&weak).is_none());
+    }
+
+    #[test]
+    fn test_pin() {
+        struct Pinned(usize, PhantomPinned);
+
+        let mut x = Dynamic::pin(Pinned(5, PhantomPinned));
+        assert!(x.as_mut().downcast_pin_mut::<X>().is_none());
+
+        let pinned = x.as_mut().downcast_pin_mut::<Pinned>().unwrap();
+        unsafe { pinned.get_unchecked_mut().0 += 1 };
+        assert_eq!(x.downcast_ref::<Pinned>().unwrap().0, 6);
+    }
+
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn test_new_in() {
+        use std::alloc::{AllocError, Allocator};
+        use std::ptr::NonNull;
+
+        #[repr(align(16))]
+        struct Bump {
+            buffer: [Cell<u8>; 1024],
+            used: Cell<usize>,
+            freed: Cell<usize>
+        }
+
+        unsafe impl Allocator for &Bump {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                let start = (self.used.get() + layout.align() - 1) & !(layout.align() - 1);
+                let end = start + layout.size();
+                if layout.align() > 16 || end > self.buffer.len() { return Err(AllocError) }
+
+                self.used.set(end);
+                let ptr = self.buffer[start..end].as_ptr() as *mut u8;
+                Ok(NonNull::slice_from_raw_parts(NonNull::new(ptr).unwrap(), layout.size()))
+            }
+
+            unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
+                self.freed.set(self.freed.get() + 1);
+            }
+        }
+
+        let bump = Bump {
+            buffer: [const { Cell::new(0) }; 1024],
+            used: Cell::new(0),
+            freed: Cell::new(0)
+        };
+
+        let x = Dynamic::new_in(X(9), &bump);
+        assert!(bump.used.get() > 0);
+        assert!(x.is::<X>());
+
+        let x = Dynamic::downcast_in::<String, _>(x).unwrap_err();
+        let described = Dynamic::downcast_in::<X, _>(x).unwrap();
+        assert_eq!(described.data.0, 9);
+        assert_eq!(bump.freed.get(), 0);
+
+        drop(described);
+        assert_eq!(bump.freed.get(), 1);
+
+        drop(Dynamic::new_in(String::from("bump"), &bump));
+        assert_eq!(bump.freed.get(), 2);
+    }
+
+    #[test]
+    fn test_descriptor_drop_fn() {
+        let drops = Rc::new(Cell::new(0));
+        let mut val = ManuallyDrop::new(Dropper(drops.clone()));
+
+        unsafe {
+            let drop_fn = Descriptor::new::<Dropper>().drop_fn();
+            drop_fn(&mut *val as *mut Dropper as *mut ());
+        }
+
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn test_descriptor_clone_fn() {
+        assert!(Descriptor::new::<String>().clone_fn().is_none());
+
+        let desc = Descriptor::new_cloneable::<String>();
+        assert_eq!(desc, Descriptor::new::<String>());
+
+        let original = String::from("hello");
+        let cloned = unsafe {
+            let clone_fn = desc.clone_fn().unwrap();
+            Box::from_raw(clone_fn(&original as *const String as *const ()) as *mut String)
+        };
+
+        assert_eq!(*cloned, original);
+        assert!(cloned.as_ptr() != original.as_ptr());
+
+        let x = Dynamic::new_cloneable(String::from("world"));
+        assert!(x.descriptor().clone_fn().is_some());
+        assert!(Dynamic::new(String::new()).descriptor().clone_fn().is_none());
+    }
+
+    #[test]
+    fn test_clone() {
+        let original = Dynamic::new_cloneable(String::from("hello"));
+        let cloned = original.clone();
+
+        assert!(!ptr::addr_eq(&*cloned, &*original));
+        assert_eq!(cloned.descriptor(), original.descriptor());
+        assert!(cloned.descriptor().clone_fn().is_some());
+
+        let (original, cloned) = (original.downcast_ref::<String>().unwrap(),
+                                  cloned.downcast_ref::<String>().unwrap());
+        assert_eq!(cloned, original);
+        assert!(cloned.as_ptr() != original.as_ptr());
+
+        let values = vec![Dynamic::new_cloneable(1), Dynamic::new_cloneable(X(2))];
+        let copies = values.clone();
+        assert_eq!(copies[0].downcast_ref::<i32>(), Some(&1));
+        assert_eq!(copies[1].downcast_ref::<X>().unwrap().0, 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_clone_without_support() {
+        let _ = Dynamic::new(1).clone();
+    }
+
+    #[test]
+    fn test_debug_data() {
+        #[derive(Debug)]
+        struct Point { x: i32, y: i32 }
+
+        let point = Dynamic::new_debug(Point { x: 1, y: -2 });
+        assert!(point.descriptor().debug_fn().is_some());
+        assert!(format!("{:?}", point).contains("Point { x: 1, y: -2 }"));
+
+        let opaque = Dynamic::new(Point { x: 1, y: -2 });
+        assert!(opaque.descriptor().debug_fn().is_none());
+        assert!(!format!("{:?}", opaque).contains("x: 1"));
+        let p = opaque.downcast_ref::<Point>().unwrap();
+        assert_eq!((p.x, p.y), (1, -2));
+
+        // Formatter flags are forwarded to the contained value.
+        let pretty = format!("{:#?}", point);
+        assert!(pretty.contains("Point {\n") && pretty.contains("x: 1,\n"));
+
+        let values = vec![Dynamic::new_debug(Some(3)), Dynamic::new_debug("s")];
+        let debug = format!("{:?}", values);
+        assert!(debug.contains("data: Some(3)") && debug.contains("data: \"s\""));
+    }
+
+    #[test]
+    fn test_display() {
+        use std::fmt::Write;
+
+        let message = Dynamic::new_display(String::from("file not found"));
+        assert!(message.descriptor().display_fn().is_some());
+        assert_eq!(message.to_string(), "file not found");
+        assert_eq!(format!("{:>6}", Dynamic::new_display(42)), "    42");
+
+        let opaque = Dynamic::new(String::from("file not found"));
+        assert!(opaque.descriptor().display_fn().is_none());
+        assert!(write!(String::new(), "{}", opaque).is_err());
+    }
+
+    #[test]
+    fn test_error() {
+        use std::error::Error;
+        use std::fmt;
+
+        #[derive(Debug)]
+        struct ConfigError(ParseIntError);
+
+        impl fmt::Display for ConfigError {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("invalid config")
+            }
+        }
+
+        impl Error for ConfigError {
+            fn source(&self) -> Option<&(dyn Error + 'static)> { Some(&self.0) }
+        }
+
+        let error = Dynamic::new_error(ConfigError("x".parse::<u8>().unwrap_err()));
+        assert_eq!(error.to_string(), "invalid config");
+        assert!(format!("{:?}", error).contains("ConfigError"));
+
+        let as_error = error.as_error().unwrap();
+        assert!(as_error.is::<ConfigError>());
+        assert_eq!(as_error.to_string(), "invalid config");
+        assert!(as_error.source().unwrap().is::<ParseIntError>());
+
+        let boxed = error.into_error().unwrap();
+        assert!(boxed.downcast_ref::<ConfigError>().is_some());
+        assert_eq!(boxed.source().unwrap().to_string(), "invalid digit found in string");
+
+        let opaque = Dynamic::new(String::from("not an error"));
+        assert!(opaque.as_error().is_none());
+        assert!(opaque.into_error().is_err());
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = Dynamic::new_eq(String::from("a"));
+
+        assert!(a == Dynamic::new_eq(String::from("a")));
+        assert!(a != Dynamic::new_eq(String::from("b")));
+        assert!(a != Dynamic::new_eq(1));
+        assert!(a != Dynamic::new(1));
+
+        let (a, b): (&Dynamic, &Dynamic) = (&Dynamic::new_eq(1), &Dynamic::new_ord(1));
+        assert!(*a == *b);
+
+        let values = vec![Dynamic::new_eq(1), Dynamic::new_hash(String::from("a"))];
+        assert_eq!(values, vec![Dynamic::new_eq(1), Dynamic::new_eq(String::from("a"))]);
+        assert!(values != vec![Dynamic::new_eq(String::from("a")), Dynamic::new_eq(1)]);
+    }
+
+    #[test]
+    fn test_eq_concrete() {
+        let a = Dynamic::new(String::from("a"));
+        let (expected, unexpected) = (String::from("a"), String::from("b"));
+
+        assert!(*a == expected);
+        assert!(*a != unexpected);
+        assert!(*a != 1);
+        assert!(*Dynamic::new(1u8) != 1i32);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_eq_without_support() {
+        let _ = Dynamic::new_eq(1) == Dynamic::new(1);
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut set = HashSet::new();
+
+        assert!(set.insert(Dynamic::new_hash(String::from("a"))));
+        assert!(set.insert(Dynamic::new_hash(String::from("b"))));
+        assert!(set.insert(Dynamic::new_hash(7)));
+        assert!(!set.insert(Dynamic::new_hash(String::from("a"))));
+
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&Dynamic::new_hash(7)));
+        assert!(!set.contains(&Dynamic::new_hash(7u8)));
+    }
+
+    #[test]
+    fn test_hash_consistent_with_eq() {
+        fn hash_of<H: Hash + ?Sized>(val: &H) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            val.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let (a, b) = (Dynamic::new_hash(vec![1, 2]), Dynamic::new_hash(vec![1, 2]));
+        assert!(a == b);
+        assert_eq!(hash_of(&*a), hash_of(&*b));
+        assert!(hash_of(&*a) != hash_of(&*Dynamic::new_hash(vec![2, 1])));
+
+        let mut cache = HashMap::new();
+        cache.insert(a, "cached");
+        assert_eq!(cache.get(&b), Some(&"cached"));
+        assert_eq!(cache.get(&Dynamic::new_hash(vec![1u8, 2])), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hash_without_support() {
+        HashSet::new().insert(Dynamic::new_eq(1));
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut values = [
+            Dynamic::new_ord(3), Dynamic::new_ord(String::from("b")),
+            Dynamic::new_ord(1), Dynamic::new_ord(String::from("a")),
+            Dynamic::new_ord(2)
+        ];
+        values.sort();
+
+        let ints = values.iter().filter_map(|v| v.downcast_ref::<i32>().cloned());
+        assert_eq!(ints.collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        let strings = values.iter().filter_map(|v| v.downcast_ref::<String>().cloned());
+        assert_eq!(strings.collect::<Vec<_>>(), vec!["a", "b"]);
+
+        // Values of each type are grouped together.
+        let ids = values.iter().map(|v| v.id()).collect::<Vec<_>>();
+        assert!(ids.windows(2).filter(|w| w[0] != w[1]).count() == 1);
+
+        assert!(Dynamic::new_ord(1) == Dynamic::new_ord(1));
+    }
+
+    #[test]
+    fn test_make_default() {
+        assert!(Descriptor::new::<String>().default_fn().is_none());
+        assert!(Descriptor::new::<String>().make_default().is_none());
+
+        let desc = Descriptor::new_default::<Vec<i32>>();
+        let default = desc.make_default().unwrap();
+        assert_eq!(default.descriptor(), desc);
+        assert!(default.descriptor().default_fn().is_some());
+        assert_eq!(default.downcast::<Vec<i32>>().unwrap().data, Vec::<i32>::new());
+
+        let raw = unsafe { Box::from_raw(desc.default_fn().unwrap()() as *mut Vec<i32>) };
+        assert!(raw.is_empty());
+    }
+
+    #[test]
+    fn test_zero_sized() {
+        assert!(Descriptor::new::<Y>().is_zero_sized());
+        assert!(Descriptor::new::<()>().is_zero_sized());
+        assert!(!Descriptor::new::<X>().is_zero_sized());
+
+        let y = Dynamic::new(Y);
+        assert!(y.is_zero_sized());
+        assert!(y.is::<Y>());
+        assert!(!Dynamic::new(X(1)).is_zero_sized());
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(Descriptor::new::<X>().layout(), Layout::new::<X>());
+        assert_eq!(Descriptor::new::<Y>().layout(), Layout::new::<Y>());
+        assert_eq!(Dynamic::new(0u16).layout(), Layout::new::<u16>());
+
+        let desc = Descriptor::new::<u32>();
+        assert_eq!(desc.size(), 4);
+        assert_eq!(desc.alignment(), mem::align_of::<u32>());
+    }
+
+    #[test]
+    fn test_layout_compatible() {
+        let x = Descriptor::new::<X>();
+
+        assert!(x.layout_compatible(&x));
+        assert!(x.layout_compatible(&Descriptor::new::<Z>()));
+        assert!(x != Descriptor::new::<Z>());
+
+        assert!(!x.layout_compatible(&Descriptor::new::<Y>()));
+        assert!(!Descriptor::new::<u32>().layout_compatible(&Descriptor::new::<[u8; 4]>()));
+    }
+
+    #[test]
+    fn test_descriptor_info() {
+        let desc = Descriptor::new::<u64>();
+
+        assert_eq!(desc.info(), DescriptorInfo {
+            name: String::from("u64"),
+            size: 8,
+            alignment: desc.alignment()
+        });
+        assert_eq!(DescriptorInfo::from(&desc), desc.info());
+    }
+
+    #[test]
+    fn test_descriptor_meta() {
+        #[derive(Debug, PartialEq)]
+        struct SchemaVersion(u32);
+
+        let desc = Descriptor::with_meta::<X>(SchemaVersion(2));
+        assert_eq!(desc.meta(), &SchemaVersion(2));
+        assert_eq!(desc.id(), TypeId::of::<X>());
+        assert_eq!(desc.without_meta(), Descriptor::new::<X>());
+        assert!(format!("{:?}", desc).contains("SchemaVersion(2)"));
+
+        let cloneable = Descriptor::new_cloneable::<String>().attach_meta("tag");
+        assert_eq!(*cloneable.meta(), "tag");
+        assert!(cloneable.clone_fn().is_some());
+
+        let _: &() = Dynamic::new(X(1)).descriptor().meta();
+    }
+
+    #[test]
+    fn test_raw_layout() {
+        let desc = Descriptor::new::<(u8, u32)>();
+        assert_eq!(desc.raw_layout(), RawLayout { size: 8, alignment: 4 });
+
+        // The layout fields lead the descriptor, so they can be read through
+        // a pointer to it.
+        let raw = unsafe { *(&desc as *const Descriptor as *const RawLayout) };
+        assert_eq!(raw, desc.raw_layout());
+    }
+
+    #[test]
+    fn test_descriptor_hash() {
+        let mut handlers = HashMap::new();
+        handlers.insert(Descriptor::new::<X>(), "x");
+        handlers.insert(Descriptor::new::<Y>(), "y");
+        handlers.insert(Descriptor::new::<X>(), "another x");
+
+        assert_eq!(handlers.len(), 2);
+        assert_eq!(handlers[&Dynamic::new(X(1)).descriptor()], "another x");
+        assert_eq!(handlers[&Descriptor::new::<Y>()], "y");
+        assert!(!handlers.contains_key(&Descriptor::new::<Z>()));
+    }
+
+    #[test]
+    fn test_descriptor_ord() {
+        let (x, y, z) = (Descriptor::new::<X>(), Descriptor::new::<Y>(),
+                         Descriptor::new::<Z>());
+
+        let mut descs = vec![z, x, y, x];
+        descs.sort();
+        descs.dedup();
+
+        assert_eq!(descs.len(), 3);
+        assert!(descs[0] < descs[1] && descs[1] < descs[2]);
+        assert_eq!(x.cmp(&x), x.id().cmp(&x.id()));
+        assert_eq!(x.cmp(&y), x.id().cmp(&y.id()));
     }
 }
 
@@ -189,8 +3045,10 @@ mod bench {
     extern crate test;
 
     use std::any::Any;
+    use std::boxed::Box;
     use Dynamic;
 
+    #[allow(dead_code)]
     struct X(usize);
 
     #[bench]
@@ -209,7 +3067,7 @@ mod bench {
 
     #[bench]
     fn bench_any_downcast(b: &mut test::Bencher) {
-        let mut x = Box::new(X(100)) as Box<Any>;
+        let mut x = Box::new(X(100)) as Box<dyn Any>;
 
         // Needed or else the benchmark gets optimized away.
         test::black_box(&mut x);
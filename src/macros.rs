@@ -0,0 +1,139 @@
+//! Macros for constructing and dispatching on Dynamic values.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use Dynamic;
+
+/// Create Dynamic values.
+///
+/// `dynamic!(val)` is equivalent to `Dynamic::new(val)`, and
+/// `dynamic![a, b, c]` creates a `Vec<Box<Dynamic>>` containing each of the
+/// values as a Dynamic, in order:
+///
+/// ```
+/// #[macro_use]
+/// extern crate dynamic;
+///
+/// # fn main() {
+/// let values = dynamic![1i32, String::from("two"), 3.0f64];
+///
+/// assert_eq!(values.len(), 3);
+/// assert_eq!(values[1].downcast_ref::<String>().unwrap(), "two");
+/// assert!(dynamic!(4u8).is::<u8>());
+/// # }
+/// ```
+///
+/// Since a single value creates a single Dynamic, a vector of one value
+/// needs a trailing comma, as in `dynamic![val,]`.
+#[macro_export]
+macro_rules! dynamic {
+    ($val:expr) => {
+        $crate::Dynamic::new($val)
+    };
+    ($($val:expr),* $(,)*) => {
+        $crate::__dynamic_vec([$($crate::Dynamic::new($val)),*])
+    };
+}
+
+/// Dispatch on the type of a Dynamic value.
+///
+/// `match_dynamic!(val, { x: A => .., y: B => .., _ => .. })` evaluates the
+/// body of the first arm whose type matches the contained type of `val`,
+/// with the contained value bound by reference to the arm's name, or the
+/// body of the catch-all arm if none match:
+///
+/// ```
+/// #[macro_use]
+/// extern crate dynamic;
+///
+/// use dynamic::Dynamic;
+///
+/// fn describe(val: &Dynamic) -> String {
+///     match_dynamic!(val, {
+///         x: i32 => format!("the number {}", x),
+///         s: String => format!("the string {:?}", s),
+///         _ => format!("a `{}`", val.descriptor().name()),
+///     })
+/// }
+///
+/// # fn main() {
+/// assert_eq!(describe(&Dynamic::new(1i32)), "the number 1");
+/// assert_eq!(describe(&Dynamic::new(String::from("one"))), "the string \"one\"");
+/// assert_eq!(describe(&Dynamic::new(1u8)), "a `u8`");
+/// # }
+/// ```
+///
+/// `val` may be anything that dereferences to a Dynamic, such as a
+/// `Box<Dynamic>`. Every arm, including the last, is followed by a comma,
+/// and the catch-all arm is required.
+#[macro_export]
+macro_rules! match_dynamic {
+    ($val:expr, { $($name:ident : $ty:ty => $body:expr,)* _ => $default:expr $(,)* }) => {{
+        #[allow(unused_variables)]
+        let value: &$crate::Dynamic = &$val;
+        $(if let Some($name) = value.downcast_ref::<$ty>() { $body } else)* { $default }
+    }};
+}
+
+// Used by `dynamic!`, to create vectors without relying on std.
+#[doc(hidden)]
+#[inline]
+pub fn __dynamic_vec<const N: usize>(vals: [Box<Dynamic>; N]) -> Vec<Box<Dynamic>> {
+    Vec::from(vals)
+}
+
+#[cfg(test)]
+mod test {
+    use std::format;
+    use std::string::String;
+
+    use Dynamic;
+
+    #[derive(Debug, PartialEq)]
+    struct X(usize);
+
+    #[test]
+    fn test_dynamic_macro() {
+        let values = dynamic![1i32, String::from("two"), X(3)];
+
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[0].downcast_ref::<i32>(), Some(&1));
+        assert_eq!(values[1].downcast_ref::<String>().unwrap(), "two");
+        assert_eq!(values[2].downcast_ref::<X>(), Some(&X(3)));
+
+        assert_eq!(dynamic!(X(4)).downcast_ref::<X>(), Some(&X(4)));
+        assert_eq!(dynamic![X(5),].len(), 1);
+        assert_eq!(dynamic![1u8, 2u8,].len(), 2);
+        assert!(dynamic![].is_empty());
+    }
+
+    #[test]
+    fn test_match_dynamic() {
+        fn classify(val: &Dynamic) -> String {
+            match_dynamic!(val, {
+                x: i32 => format!("int {}", x + 1),
+                s: String => format!("string {}", s.len()),
+                x: X => format!("X {}", x.0),
+                _ => String::from("other"),
+            })
+        }
+
+        assert_eq!(classify(&Dynamic::new(1i32)), "int 2");
+        assert_eq!(classify(&Dynamic::new(String::from("abc"))), "string 3");
+        assert_eq!(classify(&Dynamic::new(X(4))), "X 4");
+        assert_eq!(classify(&Dynamic::new(1u8)), "other");
+
+        // Earlier arms take precedence over later arms of the same type.
+        let boxed = Dynamic::new(5i32);
+        let first = match_dynamic!(boxed, {
+            x: i32 => *x,
+            y: i32 => -*y,
+            _ => 0
+        });
+        assert_eq!(first, 5);
+
+        let fallback = match_dynamic!(boxed, { _ => "fallback" });
+        assert_eq!(fallback, "fallback");
+    }
+}
@@ -0,0 +1,193 @@
+//! A map holding at most one value of each type.
+
+use std::any::{Any, TypeId};
+use std::boxed::Box;
+use std::collections::hash_map::{self, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+
+use Dynamic;
+
+/// A map from types to a single value of each type.
+///
+/// Values are stored as `Dynamic`s keyed by the `TypeId` of their type, so
+/// retrieving a value is a hash lookup followed by a downcast which cannot
+/// fail.
+#[derive(Debug, Default)]
+pub struct DynamicMap {
+    map: HashMap<TypeId, Box<Dynamic>>
+}
+
+impl DynamicMap {
+    /// Create a new, empty map.
+    #[inline]
+    pub fn new() -> DynamicMap { DynamicMap::default() }
+
+    /// Insert a value, returning the value of the same type which was
+    /// previously in the map, if any.
+    pub fn insert<T: Any>(&mut self, val: T) -> Option<T> {
+        self.map.insert(TypeId::of::<T>(), Dynamic::new(val)).map(unwrap::<T>)
+    }
+
+    /// Get a reference to the value of type `T`, if there is one.
+    #[inline]
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>()).and_then(|val| val.downcast_ref())
+    }
+
+    /// Get a mutable reference to the value of type `T`, if there is one.
+    #[inline]
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>()).and_then(|val| val.downcast_mut())
+    }
+
+    /// Remove the value of type `T` from the map, returning it if there was
+    /// one.
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.map.remove(&TypeId::of::<T>()).map(unwrap::<T>)
+    }
+
+    /// Get the entry for the value of type `T`, for in-place manipulation.
+    #[inline]
+    pub fn entry<T: Any>(&mut self) -> Entry<'_, T> {
+        Entry { inner: self.map.entry(TypeId::of::<T>()), marker: PhantomData }
+    }
+
+    /// Check if the map contains a value of type `T`.
+    #[inline]
+    pub fn contains<T: Any>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Get the number of values in the map.
+    #[inline]
+    pub fn len(&self) -> usize { self.map.len() }
+
+    /// Check if the map is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.map.is_empty() }
+}
+
+/// The entry for the value of type `T` in a `DynamicMap`, which may be
+/// occupied or vacant.
+pub struct Entry<'a, T> {
+    inner: hash_map::Entry<'a, TypeId, Box<Dynamic>>,
+    marker: PhantomData<fn() -> T>
+}
+
+impl<'a, T: Any> Entry<'a, T> {
+    /// Check if the map contains a value of type `T`.
+    #[inline]
+    pub fn is_occupied(&self) -> bool {
+        match self.inner {
+            hash_map::Entry::Occupied(_) => true,
+            hash_map::Entry::Vacant(_) => false
+        }
+    }
+
+    /// Insert the given value if the entry is vacant, and get a mutable
+    /// reference to the value in the entry.
+    #[inline]
+    pub fn or_insert(self, val: T) -> &'a mut T {
+        self.or_insert_with(|| val)
+    }
+
+    /// Insert the value returned by `f` if the entry is vacant, and get a
+    /// mutable reference to the value in the entry.
+    ///
+    /// `f` is only called if the entry is vacant.
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> T>(self, f: F) -> &'a mut T {
+        let val = self.inner.or_insert_with(|| Dynamic::new(f()));
+
+        // The entry is keyed by the id of T.
+        unsafe { val.downcast_mut_unchecked() }
+    }
+
+    /// Insert the default value if the entry is vacant, and get a mutable
+    /// reference to the value in the entry.
+    #[inline]
+    pub fn or_default(self) -> &'a mut T where T: Default {
+        self.or_insert_with(T::default)
+    }
+
+    /// Modify the value in the entry with `f`, if it is occupied.
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Entry<'a, T> {
+        Entry {
+            inner: self.inner.and_modify(|val| f(unsafe { val.downcast_mut_unchecked() })),
+            marker: PhantomData
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for Entry<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Entry").field(&self.inner).finish()
+    }
+}
+
+// Every value is keyed by the id of its own type, so this cannot fail.
+fn unwrap<T: Any>(val: Box<Dynamic>) -> T {
+    match val.into_inner() {
+        Ok(val) => val,
+        Err(_) => unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::string::String;
+
+    use DynamicMap;
+
+    #[derive(Debug, PartialEq)]
+    struct X(usize);
+
+    #[test]
+    fn test_dynamic_map() {
+        let mut map = DynamicMap::new();
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert(1i32), None);
+        assert_eq!(map.insert(String::from("one")), None);
+        assert_eq!(map.insert(X(1)), None);
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(map.insert(2i32), Some(1));
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get::<i32>(), Some(&2));
+        assert_eq!(map.get::<String>().unwrap(), "one");
+        assert_eq!(map.get::<u8>(), None);
+
+        map.get_mut::<X>().unwrap().0 = 2;
+        assert_eq!(map.get::<X>(), Some(&X(2)));
+
+        assert_eq!(map.remove::<X>(), Some(X(2)));
+        assert_eq!(map.remove::<X>(), None);
+        assert!(!map.contains::<X>());
+        assert!(map.contains::<String>());
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_dynamic_map_entry() {
+        let mut map = DynamicMap::new();
+        assert!(!map.entry::<i32>().is_occupied());
+
+        *map.entry::<i32>().or_insert_with(|| 1) += 1;
+        assert_eq!(map.get::<i32>(), Some(&2));
+        assert!(map.entry::<i32>().is_occupied());
+
+        assert_eq!(*map.entry::<i32>().or_insert_with(|| unreachable!()), 2);
+        assert_eq!(*map.entry::<i32>().or_insert(10), 2);
+
+        map.entry::<X>().and_modify(|_| unreachable!()).or_insert(X(1));
+        map.entry::<X>().and_modify(|x| x.0 += 1).or_insert(X(10));
+        assert_eq!(map.get::<X>(), Some(&X(2)));
+
+        map.entry::<String>().or_default().push_str("default");
+        assert_eq!(map.get::<String>().unwrap(), "default");
+        assert_eq!(map.len(), 3);
+    }
+}
@@ -0,0 +1,198 @@
+//! A pool recycling the allocations of freed Dynamic values.
+
+use alloc::alloc::dealloc;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::any::Any;
+use core::fmt;
+use core::ptr::NonNull;
+
+use {Described, Descriptor, Dynamic};
+
+/// A pool of allocations for Dynamic values, reused across types.
+///
+/// Freeing a Dynamic into the pool drops the contained value but keeps its
+/// allocation, which is then reused by the next value allocated from the
+/// pool whose type is `Descriptor::layout_compatible` with the type of the
+/// freed value:
+///
+/// ```
+/// use dynamic::DynamicPool;
+///
+/// let mut pool = DynamicPool::new();
+/// let x = pool.alloc(1u64);
+/// let addr = x.as_ptr();
+///
+/// pool.free(x);
+/// let y = pool.alloc(2i64);
+/// assert_eq!(y.as_ptr(), addr);
+/// assert_eq!(y.downcast_ref::<i64>(), Some(&2));
+/// ```
+#[derive(Default)]
+pub struct DynamicPool {
+    blocks: Vec<Block>
+}
+
+// A free allocation, left behind by a value of the described type.
+struct Block {
+    desc: Descriptor,
+    layout: Layout,
+    ptr: NonNull<u8>
+}
+
+impl DynamicPool {
+    /// Create a new, empty pool.
+    #[inline]
+    pub fn new() -> DynamicPool { DynamicPool::default() }
+
+    /// Create a new Dynamic value containing the given value, reusing a
+    /// free allocation from the pool if one was left by a value of a
+    /// layout compatible type.
+    pub fn alloc<T: Any>(&mut self, val: T) -> Box<Dynamic> {
+        let desc = Descriptor::new::<T>();
+
+        match self.blocks.iter().rposition(|block| block.desc.layout_compatible(&desc)) {
+            Some(index) => unsafe {
+                // Described is `#[repr(C)]`, so a Described of a layout
+                // compatible type has the layout of a Described<T>. The block
+                // was allocated by the global allocator with that layout, and
+                // holds no value.
+                let block = self.blocks.swap_remove(index);
+                debug_assert_eq!(block.layout, Layout::new::<Described<T>>());
+
+                let ptr = block.ptr.as_ptr() as *mut Described<T>;
+                ptr.write(Described::new(val));
+                Dynamic::from_described_box(Box::from_raw(ptr))
+            },
+            None => Dynamic::new(val)
+        }
+    }
+
+    /// Drop the contained value of a Dynamic, keeping its allocation in the
+    /// pool for reuse.
+    pub fn free(&mut self, val: Box<Dynamic>) {
+        let desc = val.desc;
+        let layout = Layout::for_value::<Dynamic>(&val);
+        let raw = Box::into_raw(val);
+
+        unsafe {
+            // The value is dropped in place, leaving the allocation behind.
+            (desc.drop)((*raw).as_mut_ptr());
+            self.blocks.push(Block { desc, layout, ptr: NonNull::new_unchecked(raw as *mut u8) });
+        }
+    }
+
+    /// Get the number of free allocations in the pool.
+    #[inline]
+    pub fn len(&self) -> usize { self.blocks.len() }
+
+    /// Check if the pool has no free allocations.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.blocks.is_empty() }
+
+    /// Release every free allocation in the pool.
+    pub fn clear(&mut self) {
+        for block in self.blocks.drain(..) {
+            unsafe { dealloc(block.ptr.as_ptr(), block.layout) }
+        }
+    }
+}
+
+impl Drop for DynamicPool {
+    fn drop(&mut self) { self.clear() }
+}
+
+impl fmt::Debug for DynamicPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicPool")
+            .field("free", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::string::String;
+    use std::vec;
+    use std::vec::Vec;
+
+    use {Dynamic, DynamicPool};
+
+    struct Dropper(Rc<Cell<usize>>);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    #[test]
+    fn test_dynamic_pool_reuse() {
+        let mut pool = DynamicPool::new();
+        let x = pool.alloc(String::from("x"));
+        let addr = x.as_ptr();
+
+        pool.free(x);
+        assert_eq!(pool.len(), 1);
+
+        // Vec<u8> has the same layout as String.
+        let y = pool.alloc(vec![1u8, 2, 3]);
+        assert_eq!(y.as_ptr(), addr);
+        assert_eq!(y.downcast_ref::<Vec<u8>>().unwrap(), &[1, 2, 3]);
+        assert!(pool.is_empty());
+
+        // Values of other layouts get fresh allocations.
+        pool.free(y);
+        let z = pool.alloc(1u8);
+        assert_ne!(z.as_ptr(), addr);
+        assert_eq!(z.downcast_ref::<u8>(), Some(&1));
+        assert_eq!(pool.len(), 1);
+
+        // Allocations from outside the pool can be freed into it too.
+        pool.free(Dynamic::new(2u8));
+        assert_eq!(pool.len(), 2);
+        assert_eq!(*pool.alloc(3u8).downcast_ref::<u8>().unwrap(), 3);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_dynamic_pool_layout_compatible() {
+        let mut pool = DynamicPool::new();
+
+        // A Described<[u8; 5]> is padded to the layout of a Described<u64>,
+        // but the types themselves are not layout compatible.
+        pool.free(Dynamic::new([1u8; 5]));
+        let x = pool.alloc(2u64);
+        assert_eq!(x.downcast_ref::<u64>(), Some(&2));
+        assert_eq!(pool.len(), 1);
+
+        // A smaller type does not reuse a larger allocation either.
+        pool.free(x);
+        assert_eq!(*pool.alloc(3u8).downcast_ref::<u8>().unwrap(), 3);
+        assert_eq!(pool.len(), 2);
+
+        assert_eq!(pool.alloc([4u8; 5]).downcast_ref::<[u8; 5]>(), Some(&[4; 5]));
+        assert_eq!(*pool.alloc(5i64).downcast_ref::<i64>().unwrap(), 5);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_pool_drop() {
+        let drops = Rc::new(Cell::new(0));
+        let mut pool = DynamicPool::new();
+
+        let x = pool.alloc(Dropper(drops.clone()));
+        pool.free(x);
+        assert_eq!(drops.get(), 1);
+
+        let reused = pool.alloc(Dropper(drops.clone()));
+        assert!(pool.is_empty());
+        drop(reused);
+        assert_eq!(drops.get(), 2);
+
+        pool.free(Dynamic::new(Dropper(drops.clone())));
+        drop(pool);
+        assert_eq!(drops.get(), 3);
+    }
+}
@@ -0,0 +1,373 @@
+//! Registries for constructing Dynamic values at runtime.
+
+use std::any::{Any, TypeId};
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fmt;
+use std::string::String;
+
+use Dynamic;
+
+/// A registry of named constructors for Dynamic values.
+///
+/// Useful when the type of a value is only known by name at runtime, for
+/// instance when it is read from a file or received over the network.
+#[derive(Default)]
+pub struct Registry {
+    factories: HashMap<String, Box<dyn Fn() -> Box<Dynamic>>>
+}
+
+impl Registry {
+    /// Create a new, empty registry.
+    #[inline]
+    pub fn new() -> Registry { Registry::default() }
+
+    /// Register `T` under the given name, constructed by `T::default`.
+    ///
+    /// Replaces any constructor previously registered under the same name.
+    #[inline]
+    pub fn register<T: Any + Default>(&mut self, name: &str) {
+        self.register_with(name, || Dynamic::new(T::default()))
+    }
+
+    /// Register a constructor under the given name.
+    ///
+    /// Replaces any constructor previously registered under the same name.
+    #[inline]
+    pub fn register_with<F>(&mut self, name: &str, factory: F)
+    where F: Fn() -> Box<Dynamic> + 'static {
+        self.factories.insert(name.into(), Box::new(factory));
+    }
+
+    /// Construct a new value using the constructor registered under the
+    /// given name, if there is one.
+    #[inline]
+    pub fn construct(&self, name: &str) -> Option<Box<Dynamic>> {
+        self.factories.get(name).map(|factory| factory())
+    }
+
+    /// Check if a constructor is registered under the given name.
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool { self.factories.contains_key(name) }
+
+    /// Iterate over the names with registered constructors, in arbitrary
+    /// order.
+    #[inline]
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(|name| &**name)
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+/// A registry of named plugin factories, which construct Dynamic values
+/// from arguments.
+///
+/// Like `Registry`, but each factory is given a set of `PluginArgs` to
+/// construct its plugin from.
+#[derive(Default)]
+pub struct PluginRegistry {
+    factories: HashMap<String, PluginFactory>
+}
+
+type PluginFactory = Box<dyn Fn(&PluginArgs) -> Box<Dynamic>>;
+
+impl PluginRegistry {
+    /// Create a new, empty registry.
+    #[inline]
+    pub fn new() -> PluginRegistry { PluginRegistry::default() }
+
+    /// Register a plugin factory under the given name.
+    ///
+    /// Replaces any factory previously registered under the same name.
+    #[inline]
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where F: Fn(&PluginArgs) -> Box<Dynamic> + 'static {
+        self.factories.insert(name.into(), Box::new(factory));
+    }
+
+    /// Construct a plugin using the factory registered under the given name,
+    /// if there is one.
+    #[inline]
+    pub fn instantiate(&self, name: &str, args: &PluginArgs) -> Option<Box<Dynamic>> {
+        self.factories.get(name).map(|factory| factory(args))
+    }
+
+    /// Check if a factory is registered under the given name.
+    #[inline]
+    pub fn contains(&self, name: &str) -> bool { self.factories.contains_key(name) }
+
+    /// Iterate over the names with registered factories, in arbitrary order.
+    #[inline]
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(|name| &**name)
+    }
+}
+
+impl fmt::Debug for PluginRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+/// Named, dynamically typed arguments for constructing a plugin.
+#[derive(Debug, Default)]
+pub struct PluginArgs {
+    args: HashMap<String, Box<Dynamic>>
+}
+
+impl PluginArgs {
+    /// Create a new, empty set of arguments.
+    #[inline]
+    pub fn new() -> PluginArgs { PluginArgs::default() }
+
+    /// Add an argument, replacing any argument with the same name.
+    #[inline]
+    pub fn with<T: Any>(mut self, name: &str, val: T) -> PluginArgs {
+        self.insert(name, val);
+        self
+    }
+
+    /// Insert an argument, replacing any argument with the same name.
+    #[inline]
+    pub fn insert<T: Any>(&mut self, name: &str, val: T) {
+        self.args.insert(name.into(), Dynamic::new(val));
+    }
+
+    /// Get the argument with the given name, if there is one.
+    #[inline]
+    pub fn get_dynamic(&self, name: &str) -> Option<&Dynamic> {
+        self.args.get(name).map(|arg| &**arg)
+    }
+
+    /// Get the argument with the given name, if there is one and it is a `T`.
+    #[inline]
+    pub fn get<T: Any>(&self, name: &str) -> Option<&T> {
+        self.get_dynamic(name).and_then(|arg| arg.downcast_ref())
+    }
+}
+
+/// A registry of casts from Dynamic values to trait objects.
+///
+/// For each trait, each implementing type is registered with a function
+/// converting a reference to it into a trait object. A Dynamic value
+/// containing a registered type can then be cast to the trait object
+/// without knowing its type.
+///
+/// ```
+/// use dynamic::{Dynamic, TypeRegistry};
+/// use std::fmt::Display;
+///
+/// let mut registry = TypeRegistry::new();
+/// registry.register::<i32, dyn Display>(|x| x);
+///
+/// let x = Dynamic::new(5i32);
+/// assert_eq!(registry.cast::<dyn Display>(&x).unwrap().to_string(), "5");
+/// ```
+#[derive(Default)]
+pub struct TypeRegistry {
+    // Keyed by the ids of the trait object type and the implementing type,
+    // each value is a Caster for that trait object type.
+    casters: HashMap<(TypeId, TypeId), Box<dyn Any>>
+}
+
+// Casts Dynamic values containing one particular type to Tr.
+struct Caster<Tr: ?Sized>(Box<dyn Fn(&Dynamic) -> &Tr>);
+
+impl TypeRegistry {
+    /// Create a new, empty registry.
+    #[inline]
+    pub fn new() -> TypeRegistry { TypeRegistry::default() }
+
+    /// Register the cast from `T` to the trait object type `Tr`.
+    ///
+    /// The cast is usually just the unsizing coercion, `|x| x`. Replaces any
+    /// cast previously registered for the same types.
+    pub fn register<T: Any, Tr: ?Sized + 'static>(&mut self, cast: fn(&T) -> &Tr) {
+        let caster = Caster::<Tr>(Box::new(move |dynamic| match dynamic.downcast_ref::<T>() {
+            Some(val) => cast(val),
+            None => unreachable!()
+        }));
+
+        self.casters.insert((TypeId::of::<Tr>(), TypeId::of::<T>()), Box::new(caster));
+    }
+
+    /// Check if a cast from `T` to `Tr` is registered.
+    #[inline]
+    pub fn is_registered<T: Any, Tr: ?Sized + 'static>(&self) -> bool {
+        self.casters.contains_key(&(TypeId::of::<Tr>(), TypeId::of::<T>()))
+    }
+
+    /// Cast the Dynamic value to the trait object type `Tr`, if a cast is
+    /// registered for its contained type.
+    #[inline]
+    pub fn cast<'a, Tr: ?Sized + 'static>(&self, dynamic: &'a Dynamic) -> Option<&'a Tr> {
+        self.casters.get(&(TypeId::of::<Tr>(), dynamic.id()))
+            .and_then(|caster| caster.downcast_ref::<Caster<Tr>>())
+            .map(|caster| (caster.0)(dynamic))
+    }
+}
+
+impl Dynamic {
+    /// Cast the contained value to the trait object type `Tr`, using the
+    /// casts in the given registry.
+    ///
+    /// Returns `None` if no cast to `Tr` is registered for the contained
+    /// type. Equivalent to `TypeRegistry::cast`.
+    #[inline]
+    pub fn downcast_trait<'a, Tr: ?Sized + 'static>(&'a self, registry: &TypeRegistry) -> Option<&'a Tr> {
+        registry.cast(self)
+    }
+}
+
+/// Register casts from each of the given types to a trait object.
+///
+/// `register_trait!(registry, Trait, [A, B, C])` registers the unsizing
+/// coercions from `A`, `B` and `C` to `dyn Trait` in the `TypeRegistry`
+/// `registry`. The trait may be generic, as in `Trait<u8>`.
+///
+/// ```
+/// #[macro_use]
+/// extern crate dynamic;
+///
+/// use dynamic::{Dynamic, TypeRegistry};
+/// use std::fmt::Display;
+///
+/// # fn main() {
+/// let mut registry = TypeRegistry::new();
+/// register_trait!(registry, Display, [i32, String]);
+///
+/// let x = Dynamic::new(String::from("five"));
+/// assert_eq!(x.downcast_trait::<dyn Display>(&registry).unwrap().to_string(), "five");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! register_trait {
+    ($registry:expr, $tr:path, [$($ty:ty),* $(,)*]) => {{
+        let registry: &mut $crate::TypeRegistry = &mut $registry;
+        $(registry.register::<$ty, dyn $tr>(|val| val);)*
+    }}
+}
+
+impl fmt::Debug for TypeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypeRegistry")
+            .field("casts", &self.casters.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::format;
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    use {Dynamic, PluginArgs, PluginRegistry, Registry, TypeRegistry};
+
+    trait Describe {
+        fn describe(&self) -> String;
+    }
+
+    struct X(usize);
+    struct Y;
+
+    impl Describe for X {
+        fn describe(&self) -> String { format!("X({})", self.0) }
+    }
+
+    impl Describe for Y {
+        fn describe(&self) -> String { "Y".to_string() }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::new();
+        registry.register::<i32>("int");
+        registry.register_with("greeting", || Dynamic::new(String::from("hello")));
+
+        assert!(registry.contains("int"));
+        assert_eq!(registry.construct("int").unwrap().downcast_ref::<i32>(), Some(&0));
+        assert_eq!(registry.construct("greeting").unwrap().downcast_ref::<String>().unwrap(), "hello");
+        assert!(registry.construct("unknown").is_none());
+        assert_eq!(registry.names().count(), 2);
+    }
+
+    #[test]
+    fn test_plugin_registry() {
+        let mut registry = PluginRegistry::new();
+        registry.register("x", |args| Dynamic::new(X(*args.get::<usize>("n").unwrap_or(&0))));
+        registry.register("greeter", |args| {
+            let name = args.get::<String>("name").map_or("world", |name| &**name);
+            Dynamic::new(format!("hello, {}", name))
+        });
+
+        let x = registry.instantiate("x", &PluginArgs::new().with("n", 5usize)).unwrap();
+        assert_eq!(x.downcast_ref::<X>().unwrap().0, 5);
+
+        let x = registry.instantiate("x", &PluginArgs::new()).unwrap();
+        assert_eq!(x.downcast_ref::<X>().unwrap().0, 0);
+
+        let args = PluginArgs::new().with("name", String::from("plugin"));
+        let greeter = registry.instantiate("greeter", &args).unwrap();
+        assert_eq!(greeter.downcast_ref::<String>().unwrap(), "hello, plugin");
+
+        assert!(registry.instantiate("unknown", &args).is_none());
+        assert!(args.get::<usize>("name").is_none());
+    }
+
+    #[test]
+    fn test_type_registry() {
+        let mut registry = TypeRegistry::new();
+        registry.register::<X, dyn Describe>(|x| x);
+        registry.register::<Y, dyn Describe>(|y| y);
+
+        assert!(registry.is_registered::<X, dyn Describe>());
+        assert!(!registry.is_registered::<String, dyn Describe>());
+
+        let x = Dynamic::new(X(1));
+        let y = Dynamic::new(Y);
+        assert_eq!(registry.cast::<dyn Describe>(&x).unwrap().describe(), "X(1)");
+        assert_eq!(registry.cast::<dyn Describe>(&y).unwrap().describe(), "Y");
+
+        let unregistered = Dynamic::new(String::from("unregistered"));
+        assert!(registry.cast::<dyn Describe>(&unregistered).is_none());
+        assert!(registry.cast::<dyn Send>(&x).is_none());
+    }
+
+    #[test]
+    fn test_register_trait() {
+        trait Scale<T> {
+            fn scale(&self, by: T) -> usize;
+        }
+
+        impl Scale<u8> for X {
+            fn scale(&self, by: u8) -> usize { self.0 * by as usize }
+        }
+
+        impl Scale<u8> for Y {
+            fn scale(&self, _: u8) -> usize { 0 }
+        }
+
+        impl Scale<u8> for usize {
+            fn scale(&self, by: u8) -> usize { self * by as usize * 2 }
+        }
+
+        let mut registry = TypeRegistry::new();
+        register_trait!(registry, Scale<u8>, [X, Y, usize]);
+        register_trait!(registry, Describe, [X, Y,]);
+
+        let values = [Dynamic::new(X(2)), Dynamic::new(Y), Dynamic::new(3usize)];
+        let scaled: Vec<usize> = values.iter()
+            .map(|val| val.downcast_trait::<dyn Scale<u8>>(&registry).unwrap().scale(10))
+            .collect();
+        assert_eq!(scaled, [20, 0, 60]);
+
+        assert_eq!(values[1].downcast_trait::<dyn Describe>(&registry).unwrap().describe(), "Y");
+        assert!(values[2].downcast_trait::<dyn Describe>(&registry).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+//! A Dynamic value which stores small values inline.
+
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use core::mem::{self, MaybeUninit};
+use core::{fmt, ptr};
+
+use {Descriptor, Dynamic};
+
+/// A dynamically typed value which stores values of up to `N` bytes inline.
+///
+/// Values which are larger than `N` bytes, or which are more strictly
+/// aligned than a `usize`, are stored on the heap as a `Dynamic` instead.
+/// Whether a value is stored inline is decided using the size and alignment
+/// from its descriptor.
+pub struct SmallDynamic<const N: usize> {
+    repr: Repr<N>
+}
+
+// The heap variant also keeps SmallDynamic from being Send or Sync, which
+// the inline buffer alone would not.
+enum Repr<const N: usize> {
+    Inline(Descriptor, Buffer<N>),
+    Heap(Box<Dynamic>)
+}
+
+#[repr(C)]
+struct Buffer<const N: usize> {
+    _align: [usize; 0],
+    bytes: [MaybeUninit<u8>; N]
+}
+
+impl<const N: usize> SmallDynamic<N> {
+    /// Create a new SmallDynamic value containing the given value.
+    ///
+    /// The value is stored inline if it fits, and on the heap otherwise.
+    pub fn new<T: Any>(val: T) -> SmallDynamic<N> {
+        let desc = Descriptor::new::<T>();
+
+        if desc.size() <= N && desc.alignment() <= mem::align_of::<Buffer<N>>() {
+            let mut buffer = Buffer { _align: [], bytes: [MaybeUninit::uninit(); N] };
+            unsafe { ptr::write(buffer.bytes.as_mut_ptr() as *mut T, val) };
+
+            SmallDynamic { repr: Repr::Inline(desc, buffer) }
+        } else {
+            SmallDynamic { repr: Repr::Heap(Dynamic::new(val)) }
+        }
+    }
+
+    /// Check if the contained value is stored inline.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        match self.repr {
+            Repr::Inline(..) => true,
+            Repr::Heap(_) => false
+        }
+    }
+
+    /// Read the descriptor for the contained value.
+    #[inline]
+    pub fn descriptor(&self) -> Descriptor {
+        match self.repr {
+            Repr::Inline(desc, _) => desc,
+            Repr::Heap(ref dynamic) => dynamic.descriptor()
+        }
+    }
+
+    /// Read the type id for the contained value.
+    #[inline]
+    pub fn id(&self) -> TypeId { self.descriptor().id() }
+
+    /// Check if the contained type is a `T`.
+    #[inline]
+    pub fn is<T: Any>(&self) -> bool {
+        self.id() == TypeId::of::<T>()
+    }
+
+    /// If the contained value is a `T`, get an immutable reference to it.
+    #[inline]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match self.repr {
+            Repr::Inline(desc, ref buffer) if desc.id() == TypeId::of::<T>() => {
+                Some(unsafe { &*(buffer.bytes.as_ptr() as *const T) })
+            },
+            Repr::Inline(..) => None,
+            Repr::Heap(ref dynamic) => dynamic.downcast_ref()
+        }
+    }
+
+    /// If the contained value is a `T`, get a mutable reference to it.
+    #[inline]
+    pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+        match self.repr {
+            Repr::Inline(desc, ref mut buffer) if desc.id() == TypeId::of::<T>() => {
+                Some(unsafe { &mut *(buffer.bytes.as_mut_ptr() as *mut T) })
+            },
+            Repr::Inline(..) => None,
+            Repr::Heap(ref mut dynamic) => dynamic.downcast_mut()
+        }
+    }
+}
+
+impl<const N: usize> Drop for SmallDynamic<N> {
+    fn drop(&mut self) {
+        // Heap values are dropped along with their box.
+        if let Repr::Inline(desc, ref mut buffer) = self.repr {
+            unsafe { (desc.drop)(buffer.bytes.as_mut_ptr() as *mut ()) }
+        }
+    }
+}
+
+impl<const N: usize> fmt::Debug for SmallDynamic<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SmallDynamic")
+            .field("descriptor", &self.descriptor())
+            .field("inline", &self.is_inline())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use SmallDynamic;
+
+    struct Dropper(Rc<Cell<usize>>);
+
+    impl Drop for Dropper {
+        fn drop(&mut self) { self.0.set(self.0.get() + 1) }
+    }
+
+    #[repr(align(64))]
+    struct Aligned(u8);
+
+    #[test]
+    fn test_small_dynamic_inline() {
+        let mut x = SmallDynamic::<16>::new(7u64);
+        assert!(x.is_inline());
+        assert!(x.is::<u64>());
+        assert!(x.downcast_ref::<u32>().is_none());
+
+        *x.downcast_mut::<u64>().unwrap() += 1;
+        assert_eq!(x.downcast_ref::<u64>(), Some(&8));
+
+        let drops = Rc::new(Cell::new(0));
+        let x = SmallDynamic::<16>::new(Dropper(drops.clone()));
+        assert!(x.is_inline());
+        assert!(Rc::ptr_eq(&x.downcast_ref::<Dropper>().unwrap().0, &drops));
+
+        let moved = x;
+        assert_eq!(drops.get(), 0);
+        drop(moved);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[test]
+    fn test_small_dynamic_spilled() {
+        let mut x = SmallDynamic::<4>::new([1u8; 32]);
+        assert!(!x.is_inline());
+        assert!(x.is::<[u8; 32]>());
+        x.downcast_mut::<[u8; 32]>().unwrap()[0] = 2;
+        assert_eq!(x.downcast_ref::<[u8; 32]>().unwrap()[..2], [2, 1]);
+
+        let x = SmallDynamic::<64>::new(Aligned(3));
+        assert!(!x.is_inline());
+        assert_eq!(x.downcast_ref::<Aligned>().unwrap().0, 3);
+
+        let drops = Rc::new(Cell::new(0));
+        let x = SmallDynamic::<0>::new(Dropper(drops.clone()));
+        assert!(!x.is_inline());
+        drop(x);
+        assert_eq!(drops.get(), 1);
+    }
+}
@@ -0,0 +1,139 @@
+//! A stack of values of mixed types.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::error::Error;
+use core::fmt;
+
+use {Descriptor, Dynamic};
+
+/// A stack of dynamically typed values, with typed access to the top.
+#[derive(Debug, Default)]
+pub struct DynamicStack {
+    items: Vec<Box<Dynamic>>
+}
+
+impl DynamicStack {
+    /// Create a new, empty stack.
+    #[inline]
+    pub fn new() -> DynamicStack { DynamicStack::default() }
+
+    /// Push a value onto the stack.
+    #[inline]
+    pub fn push<T: Any>(&mut self, val: T) {
+        self.items.push(Dynamic::new(val))
+    }
+
+    /// Push a Dynamic value onto the stack.
+    #[inline]
+    pub fn push_dynamic(&mut self, val: Box<Dynamic>) {
+        self.items.push(val)
+    }
+
+    /// If the top of the stack is a `T`, pop it.
+    ///
+    /// If the stack is empty or the top is not a `T`, the stack is left
+    /// unchanged and an error is returned.
+    #[allow(clippy::result_large_err)]
+    pub fn pop<T: Any>(&mut self) -> Result<T, PopError> {
+        match self.items.last() {
+            None => return Err(PopError::Empty),
+            Some(top) if !top.is::<T>() => return Err(PopError::WrongType(top.descriptor())),
+            Some(_) => ()
+        }
+
+        match self.items.pop().map(|top| top.into_inner()) {
+            Some(Ok(val)) => Ok(val),
+            _ => unreachable!()
+        }
+    }
+
+    /// Pop the top of the stack, whatever its type.
+    #[inline]
+    pub fn pop_dynamic(&mut self) -> Option<Box<Dynamic>> { self.items.pop() }
+
+    /// If the top of the stack is a `T`, get a reference to it.
+    #[inline]
+    pub fn peek<T: Any>(&self) -> Option<&T> {
+        self.items.last().and_then(|top| top.downcast_ref())
+    }
+
+    /// Check if the top of the stack is a `T`.
+    #[inline]
+    pub fn peek_is<T: Any>(&self) -> bool {
+        self.items.last().is_some_and(|top| top.is::<T>())
+    }
+
+    /// Get the number of values on the stack.
+    #[inline]
+    pub fn len(&self) -> usize { self.items.len() }
+
+    /// Check if the stack is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+}
+
+/// The error returned by `DynamicStack::pop`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PopError {
+    /// The stack was empty.
+    Empty,
+
+    /// The top of the stack was of another type, described by the given
+    /// descriptor.
+    WrongType(Descriptor)
+}
+
+impl fmt::Display for PopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PopError::Empty => f.write_str("the stack was empty"),
+            PopError::WrongType(desc) => write!(f, "the top of the stack was a `{}`", desc.name())
+        }
+    }
+}
+
+impl Error for PopError {}
+
+#[cfg(test)]
+mod test {
+    use std::string::{String, ToString};
+
+    use {Descriptor, DynamicStack, PopError};
+
+    #[test]
+    fn test_dynamic_stack_pop() {
+        let mut stack = DynamicStack::new();
+        stack.push(1i32);
+        stack.push(String::from("two"));
+
+        assert!(stack.peek_is::<String>());
+        assert_eq!(stack.peek::<String>().unwrap(), "two");
+        assert_eq!(stack.pop::<String>().unwrap(), "two");
+        assert_eq!(stack.pop::<i32>(), Ok(1));
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_stack_pop_wrong_type() {
+        let mut stack = DynamicStack::new();
+        stack.push(1i32);
+
+        let err = stack.pop::<String>().unwrap_err();
+        assert_eq!(err, PopError::WrongType(Descriptor::new::<i32>()));
+        assert_eq!(err.to_string(), "the top of the stack was a `i32`");
+
+        assert_eq!(stack.len(), 1);
+        assert!(stack.peek_is::<i32>());
+        assert_eq!(stack.pop::<i32>(), Ok(1));
+    }
+
+    #[test]
+    fn test_dynamic_stack_pop_empty() {
+        let mut stack = DynamicStack::new();
+        assert_eq!(stack.pop::<i32>(), Err(PopError::Empty));
+        assert!(!stack.peek_is::<i32>());
+        assert!(stack.pop_dynamic().is_none());
+    }
+}
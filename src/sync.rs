@@ -0,0 +1,295 @@
+//! Variants of `Dynamic` which can be shared or sent between threads.
+//!
+//! `Dynamic` erases every bound of the contained value except `Any`, so it
+//! can make no assumptions about thread safety and is neither `Send` nor
+//! `Sync`. The variants here require the corresponding bounds when they are
+//! created, and carry exactly those auto traits:
+//!
+//! | Type              | Created from            | `Send` | `Sync` |
+//! |-------------------|-------------------------|--------|--------|
+//! | `Dynamic`         | `T: Any`                | no     | no     |
+//! | `DynamicSend`     | `T: Any + Send`         | yes    | no     |
+//! | `DynamicSync`     | `T: Any + Sync`         | no     | yes    |
+//! | `DynamicSendSync` | `T: Any + Send + Sync`  | yes    | yes    |
+//!
+//! A `Box<DynamicSend>` can be moved to another thread, and a `&DynamicSync`
+//! can be shared with other threads. Note that `&DynamicSync` being `Send`
+//! does not make `Box<DynamicSync>` `Send`: the contained value may rely on
+//! being dropped on the thread that created it.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::any::{Any, TypeId};
+use core::{fmt, mem};
+
+use {Described, Descriptor};
+
+// Defines a variant of Dynamic whose contained value is known to implement
+// the given auto traits, so the variant implements them as well.
+//
+// The bounds are recorded by erasing the value to a trait object of a trait
+// with the bounds as supertraits, which the compiler then takes into account
+// when deciding whether the variant implements them.
+macro_rules! dynamic_variant {
+    ($(#[$attr:meta])* pub struct $name:ident: $erased:ident, $($bound:ident),+) => {
+        $(#[$attr])*
+        #[repr(C)]
+        pub struct $name {
+            desc: Descriptor,
+            data: dyn $erased
+        }
+
+        impl $name {
+            /// Create a new, heap-allocated value containing the given value.
+            ///
+            /// The result can be downcasted back to a `T`.
+            #[inline]
+            pub fn new<T: Any $(+ $bound)+>(val: T) -> Box<$name> {
+                let un_sized = Box::new(Described::new(val)) as Box<Described<dyn $erased>>;
+
+                // Laid out like Dynamic, see its definition.
+                unsafe { Box::from_raw(Box::into_raw(un_sized) as *mut $name) }
+            }
+
+            /// Create a new, atomically reference-counted value containing the
+            /// given value.
+            ///
+            /// The result can be downcasted back to a `Described<T>` with
+            /// `downcast_arc`.
+            #[inline]
+            pub fn new_arc<T: Any $(+ $bound)+>(val: T) -> Arc<$name> {
+                let un_sized = Arc::new(Described::new(val)) as Arc<Described<dyn $erased>>;
+
+                unsafe { Arc::from_raw(Arc::into_raw(un_sized) as *const $name) }
+            }
+
+            /// Read the type id for the contained value.
+            #[inline]
+            pub fn id(&self) -> TypeId { self.desc.id }
+
+            /// Read the descriptor for the contained value.
+            #[inline]
+            pub fn descriptor(&self) -> Descriptor { self.desc }
+
+            /// Check if the contained type is a `T`.
+            #[inline(always)]
+            pub fn is<T: Any>(&self) -> bool {
+                self.desc.id == TypeId::of::<T>()
+            }
+
+            /// If the contained value is a `T`, downcast back to it.
+            ///
+            /// If the value is not a `T`, returns `Err(self)`.
+            #[inline]
+            pub fn downcast<T: Any>(self: Box<Self>) -> Result<Box<Described<T>>, Box<Self>> {
+                if self.is::<T>() {
+                    Ok(unsafe { Box::from_raw(Box::into_raw(self) as *mut Described<T>) })
+                } else {
+                    Err(self)
+                }
+            }
+
+            /// If the contained value is a `T`, downcast an atomically shared
+            /// value back to it.
+            ///
+            /// The reference count is preserved. If the value is not a `T`,
+            /// returns `Err(self)`.
+            #[inline]
+            pub fn downcast_arc<T: Any>(self: Arc<Self>) -> Result<Arc<Described<T>>, Arc<Self>> {
+                if self.is::<T>() {
+                    Ok(unsafe { Arc::from_raw(Arc::into_raw(self) as *const Described<T>) })
+                } else {
+                    Err(self)
+                }
+            }
+
+            /// If the contained value is a `T`, get an immutable reference to it.
+            #[inline]
+            pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+                if self.is::<T>() {
+                    Some(unsafe { &*(&self.data as *const dyn $erased as *const T) })
+                } else {
+                    None
+                }
+            }
+
+            /// If the contained value is a `T`, get a mutable reference to it.
+            #[inline]
+            pub fn downcast_mut<T: Any>(&mut self) -> Option<&mut T> {
+                if self.is::<T>() {
+                    Some(unsafe { &mut *(&mut self.data as *mut dyn $erased as *mut T) })
+                } else {
+                    None
+                }
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("descriptor", &self.desc)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        trait $erased: $($bound +)+ {}
+        impl<T: $($bound +)+> $erased for T {}
+    }
+}
+
+dynamic_variant! {
+    /// A dynamically typed value which can be sent between threads.
+    ///
+    /// Like `Dynamic`, but can only be created from values which are `Send`,
+    /// so `DynamicSend` is `Send` too. It is not `Sync`, since the contained
+    /// value need not be.
+    pub struct DynamicSend: DynSend, Send
+}
+
+dynamic_variant! {
+    /// A dynamically typed value which can be shared between threads.
+    ///
+    /// Like `Dynamic`, but can only be created from values which are `Sync`,
+    /// so `DynamicSync` is `Sync` too. It is not `Send`, since the contained
+    /// value need not be.
+    pub struct DynamicSync: DynSync, Sync
+}
+
+dynamic_variant! {
+    /// A dynamically typed value which can be sent and shared between threads.
+    ///
+    /// Like `Dynamic`, but can only be created from values which are both
+    /// `Send` and `Sync`, so `DynamicSendSync` is both as well.
+    pub struct DynamicSendSync: DynSendSync, Send, Sync
+}
+
+impl DynamicSendSync {
+    /// Move the contained value into a std `Any + Send + Sync` trait object.
+    ///
+    /// The value is moved into a new allocation, since a `Box<dyn Any>` must
+    /// point directly at the value without its descriptor.
+    #[inline]
+    pub fn into_any_send_sync(self: Box<Self>) -> Box<dyn Any + Send + Sync> {
+        let desc = self.desc;
+
+        unsafe {
+            // The unbox function was recorded for the type of the contained
+            // value, which is boxed in a Described of that type.
+            let data = (desc.unbox)(Box::into_raw(self) as *mut ());
+
+            // The value was Send and Sync when it was erased, and auto traits
+            // do not change the vtable.
+            let any = mem::transmute::<*mut dyn Any, *mut (dyn Any + Send + Sync)>((desc.any)(data));
+            Box::from_raw(any)
+        }
+    }
+}
+
+/// Wraps a std `Any + Send + Sync` trait object in a new DynamicSendSync.
+///
+/// Like the conversion from `Box<dyn Any>` to `Box<Dynamic>`, the trait
+/// object itself becomes the contained value, since its concrete type is
+/// not known.
+impl From<Box<dyn Any + Send + Sync>> for Box<DynamicSendSync> {
+    #[inline]
+    fn from(val: Box<dyn Any + Send + Sync>) -> Box<DynamicSendSync> { DynamicSendSync::new(val) }
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::Any;
+    use std::boxed::Box;
+    use std::rc::Rc;
+    use std::string::String;
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+    use std::vec;
+
+    use {DynamicSend, DynamicSync, DynamicSendSync};
+
+    fn assert_send<T: Send + ?Sized>() {}
+    fn assert_sync<T: Sync + ?Sized>() {}
+
+    #[test]
+    fn test_dynamic_send() {
+        let mut x = DynamicSend::new(String::from("sent"));
+        assert!(x.is::<String>());
+        assert!(!x.is::<Rc<i32>>());
+        x.downcast_mut::<String>().unwrap().push('!');
+
+        let x = thread::spawn(move || {
+            assert_eq!(x.downcast_ref::<String>().unwrap(), "sent!");
+            x
+        }).join().unwrap();
+
+        let described = x.downcast::<String>().unwrap();
+        assert_eq!(described.data, "sent!");
+    }
+
+    #[test]
+    fn test_dynamic_sync() {
+        let x = DynamicSync::new(vec![1, 2, 3]);
+
+        thread::scope(|s| {
+            let handles: Vec<_> = (0..4).map(|_| {
+                let x = &*x;
+                s.spawn(move || x.downcast_ref::<Vec<i32>>().unwrap().iter().sum::<i32>())
+            }).collect();
+
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), 6);
+            }
+        });
+
+        assert!(x.downcast_ref::<Vec<u8>>().is_none());
+        assert_eq!(x.downcast::<Vec<i32>>().unwrap().data, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_dynamic_send_sync() {
+        assert_send::<DynamicSend>();
+        assert_sync::<DynamicSync>();
+        assert_send::<DynamicSendSync>();
+        assert_sync::<DynamicSendSync>();
+
+        let x = DynamicSendSync::new(7u32);
+        thread::scope(|s| {
+            s.spawn(|| assert_eq!(x.downcast_ref::<u32>(), Some(&7)));
+        });
+
+        let x = thread::spawn(move || x).join().unwrap();
+        assert_eq!(x.downcast::<u32>().unwrap().data, 7);
+    }
+
+    #[test]
+    fn test_dynamic_send_sync_arc() {
+        let x = DynamicSendSync::new_arc(String::from("shared"));
+        let handles: Vec<_> = (0..2).map(|_| {
+            let x = x.clone();
+            thread::spawn(move || x.downcast_arc::<String>().unwrap().data.len())
+        }).collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 6);
+        }
+
+        assert_eq!(Arc::strong_count(&x), 1);
+        let x = x.downcast_arc::<u8>().unwrap_err();
+        assert_eq!(x.downcast_arc::<String>().unwrap().data, "shared");
+    }
+
+    #[test]
+    fn test_any_send_sync() {
+        let any = DynamicSendSync::new(String::from("any")).into_any_send_sync();
+        let any = thread::spawn(move || {
+            assert_eq!(any.downcast_ref::<String>().unwrap(), "any");
+            any
+        }).join().unwrap();
+
+        let wrapped = Box::<DynamicSendSync>::from(any);
+        let wrapped = thread::spawn(move || wrapped).join().unwrap();
+        let any = wrapped.downcast::<Box<dyn Any + Send + Sync>>().unwrap().data;
+        assert_eq!(*any.downcast::<String>().unwrap(), "any");
+    }
+}
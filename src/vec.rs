@@ -0,0 +1,174 @@
+//! A vector of values of mixed types.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::iter::FromIterator;
+use core::ops::{Deref, DerefMut};
+
+use Dynamic;
+
+/// A vector of dynamically typed values.
+///
+/// Dereferences to the underlying `Vec<Box<Dynamic>>`, and adds methods for
+/// working with the values of a single type.
+#[derive(Debug, Default)]
+pub struct DynamicVec {
+    items: Vec<Box<Dynamic>>
+}
+
+impl DynamicVec {
+    /// Create a new, empty vector.
+    #[inline]
+    pub fn new() -> DynamicVec { DynamicVec::default() }
+
+    /// Append a value to the end of the vector.
+    #[inline]
+    pub fn push_value<T: Any>(&mut self, val: T) {
+        self.items.push(Dynamic::new(val))
+    }
+
+    /// Iterate over the values of type `T`, in order.
+    #[inline]
+    pub fn iter_as<'a, T: Any>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        downcast_iter(&self.items)
+    }
+
+    /// Iterate mutably over the values of type `T`, in order.
+    #[inline]
+    pub fn iter_as_mut<'a, T: Any>(&'a mut self) -> impl Iterator<Item = &'a mut T> + 'a {
+        downcast_iter_mut(&mut self.items)
+    }
+
+    /// Count the values of type `T`.
+    #[inline]
+    pub fn count_of<T: Any>(&self) -> usize { self.iter_as::<T>().count() }
+
+    /// Unwrap the underlying vector.
+    #[inline]
+    pub fn into_inner(self) -> Vec<Box<Dynamic>> { self.items }
+}
+
+/// Iterate over the values of type `T` in a slice of Dynamic values, in
+/// order.
+///
+/// Values of other types are skipped.
+///
+/// ```
+/// use dynamic::{Dynamic, downcast_iter};
+///
+/// let values = [Dynamic::new(1u8), Dynamic::new("two"), Dynamic::new(3u8)];
+/// assert_eq!(downcast_iter::<u8>(&values).collect::<Vec<_>>(), [&1, &3]);
+/// ```
+#[inline]
+pub fn downcast_iter<T: Any>(slice: &[Box<Dynamic>]) -> impl Iterator<Item = &T> {
+    let id = TypeId::of::<T>();
+
+    // Only values with the id of T are downcasted.
+    slice.iter()
+        .filter(move |item| item.descriptor().id() == id)
+        .map(|item| unsafe { item.downcast_ref_unchecked::<T>() })
+}
+
+/// Iterate mutably over the values of type `T` in a slice of Dynamic values,
+/// in order.
+///
+/// Values of other types are skipped.
+#[inline]
+pub fn downcast_iter_mut<T: Any>(slice: &mut [Box<Dynamic>]) -> impl Iterator<Item = &mut T> {
+    let id = TypeId::of::<T>();
+
+    // Only values with the id of T are downcasted.
+    slice.iter_mut()
+        .filter(move |item| item.descriptor().id() == id)
+        .map(|item| unsafe { item.downcast_mut_unchecked::<T>() })
+}
+
+impl Deref for DynamicVec {
+    type Target = Vec<Box<Dynamic>>;
+
+    #[inline]
+    fn deref(&self) -> &Vec<Box<Dynamic>> { &self.items }
+}
+
+impl DerefMut for DynamicVec {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Vec<Box<Dynamic>> { &mut self.items }
+}
+
+impl From<Vec<Box<Dynamic>>> for DynamicVec {
+    #[inline]
+    fn from(items: Vec<Box<Dynamic>>) -> DynamicVec { DynamicVec { items } }
+}
+
+impl Extend<Box<Dynamic>> for DynamicVec {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Box<Dynamic>>>(&mut self, iter: I) { self.items.extend(iter) }
+}
+
+impl FromIterator<Box<Dynamic>> for DynamicVec {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Box<Dynamic>>>(iter: I) -> DynamicVec {
+        DynamicVec { items: iter.into_iter().collect() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::string::String;
+    use std::vec::Vec;
+
+    use {Dynamic, DynamicVec, downcast_iter, downcast_iter_mut};
+
+    #[derive(Debug, PartialEq)]
+    struct X(usize);
+
+    #[test]
+    fn test_dynamic_vec() {
+        let mut vec = DynamicVec::new();
+        vec.push_value(1i32);
+        vec.push_value(String::from("two"));
+        vec.push_value(X(3));
+        vec.push_value(4i32);
+        vec.push(Dynamic::new(5i32));
+
+        assert_eq!(vec.len(), 5);
+        assert_eq!(vec.iter_as::<i32>().collect::<Vec<_>>(), [&1, &4, &5]);
+        assert_eq!(vec.iter_as::<String>().collect::<Vec<_>>(), ["two"]);
+        assert_eq!(vec.count_of::<i32>(), 3);
+        assert_eq!(vec.count_of::<X>(), 1);
+        assert_eq!(vec.count_of::<u8>(), 0);
+
+        for x in vec.iter_as_mut::<i32>() { *x *= 10 }
+        assert_eq!(vec.iter_as::<i32>().sum::<i32>(), 100);
+        assert_eq!(vec[2].downcast_ref::<X>(), Some(&X(3)));
+    }
+
+    #[test]
+    fn test_dynamic_vec_collect() {
+        let vec: DynamicVec = (0..3u8).map(Dynamic::new).collect();
+        assert_eq!(vec.count_of::<u8>(), 3);
+        assert_eq!(vec.into_inner().len(), 3);
+    }
+
+    #[test]
+    fn test_downcast_iter() {
+        let mut values = [
+            Dynamic::new(X(1)),
+            Dynamic::new(2i32),
+            Dynamic::new(String::from("three")),
+            Dynamic::new(X(4)),
+            Dynamic::new(5u8),
+            Dynamic::new(X(6))
+        ];
+
+        assert_eq!(downcast_iter::<X>(&values).collect::<Vec<_>>(), [&X(1), &X(4), &X(6)]);
+        assert_eq!(downcast_iter::<String>(&values).collect::<Vec<_>>(), ["three"]);
+        assert_eq!(downcast_iter::<u64>(&values).count(), 0);
+        assert_eq!(downcast_iter::<X>(&[]).count(), 0);
+
+        for (i, x) in downcast_iter_mut::<X>(&mut values[1..]).enumerate() { x.0 = i }
+        assert_eq!(downcast_iter::<X>(&values).collect::<Vec<_>>(), [&X(1), &X(0), &X(1)]);
+        assert_eq!(values[1].downcast_ref::<i32>(), Some(&2));
+    }
+}